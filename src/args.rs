@@ -0,0 +1,423 @@
+use crate::os::fixed::Placement;
+use crate::os::interrupts::RoutineCosts;
+use crate::os::memory::{MemoryOptions, VariableAllocator};
+use crate::os::mlfq::{FeedbackOptions, DEFAULT_BOOST_INTERVAL, DEFAULT_LEVELS, MAX_LEVELS};
+use crate::os::partition::FitPolicy;
+use std::fmt;
+
+const DEFAULT_PARAM_FILE: &str = "./res/OS_OSP.DAT";
+const DEFAULT_INPUT_FILE: &str = "./res/OS_INP.DAT";
+const DEFAULT_CLOCK_LIMIT: i32 = 5000;
+
+/** How much the simulator prints while it runs */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+    Full,  // allocation events, print_3 detail tables and de-allocation accounting
+    Brief, // everything except the print_3 detail tables
+}
+
+/** Command-line arguments, as checked by check_args */
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub param_file: String,
+    pub input_file: String,
+    pub debug: bool, // "Y" prints the input data list before the first simulation
+    pub clock_limit: i32, // runaway OS limit on the master clock
+    pub format: OutputFormat,
+    pub memory: MemoryOptions, // placement and compaction choices for the memory models
+    pub feedback: FeedbackOptions, // MLFQ levels and boost interval
+    pub costs: RoutineCosts,   // clock cycles taken by each OS routine
+    pub runs: Option<Vec<(usize, usize)>>, // first and last of each range of parameter records to simulate, starting at 1. None runs them all
+}
+
+/** What the command line asked for */
+pub enum Command {
+    Run(Args),
+    Help,
+}
+
+/** A command line that doesn't make sense, with the reason why */
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            param_file: DEFAULT_PARAM_FILE.to_string(),
+            input_file: DEFAULT_INPUT_FILE.to_string(),
+            debug: true,
+            clock_limit: DEFAULT_CLOCK_LIMIT,
+            format: OutputFormat::Full,
+            memory: MemoryOptions::default(),
+            feedback: FeedbackOptions::default(),
+            costs: RoutineCosts::default(),
+            runs: None,
+        }
+    }
+}
+
+impl Args {
+    /** check_args: reads the O/S parameter file, input queue file and Y/N debug flag,
+    in that order, plus any options. Missing positional arguments keep their defaults.
+    @returns
+    Ok(Command::Help) if /? or --help was given
+    Ok(Command::Run) with the arguments to simulate with
+    Err(UsageError) describing the first bad argument */
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, UsageError> {
+        let mut parsed = Args::default();
+        let mut positional = 0;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "/?" | "-h" | "--help" => return Ok(Command::Help),
+                "--clock-limit" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.clock_limit = match value.parse::<i32>() {
+                        Ok(limit) if limit > 0 => limit,
+                        _ => {
+                            return Err(UsageError(format!(
+                                "--clock-limit must be a positive number of cycles, got '{}'",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "--format" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.format = match value.to_lowercase().as_str() {
+                        "full" => OutputFormat::Full,
+                        "brief" => OutputFormat::Brief,
+                        _ => {
+                            return Err(UsageError(format!(
+                                "--format must be 'full' or 'brief', got '{}'",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "--placement" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.memory.placement = match value.to_lowercase().as_str() {
+                        "contiguous" => Placement::Contiguous,
+                        "scattered" => Placement::Scattered,
+                        _ => {
+                            return Err(UsageError(format!(
+                                "--placement must be 'contiguous' or 'scattered', got '{}'",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "--fit" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.memory.variable = match value.to_lowercase().as_str() {
+                        "first" => VariableAllocator::Partitions(FitPolicy::First),
+                        "best" => VariableAllocator::Partitions(FitPolicy::Best),
+                        "worst" => VariableAllocator::Partitions(FitPolicy::Worst),
+                        "next" => VariableAllocator::Partitions(FitPolicy::Next),
+                        "buddy" => VariableAllocator::Buddy,
+                        _ => {
+                            return Err(UsageError(format!(
+                                "--fit must be first, best, worst, next or buddy, got '{}'",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "--compact" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.memory.compact_at = match value.parse::<f64>() {
+                        Ok(percent) if (0.0..=100.0).contains(&percent) => Some(percent),
+                        _ => {
+                            return Err(UsageError(format!(
+                                "--compact must be a percentage from 0 to 100, got '{}'",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "--mlfq-levels" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.feedback.levels = match value.parse::<usize>() {
+                        Ok(levels) if (1..=MAX_LEVELS).contains(&levels) => levels,
+                        _ => {
+                            return Err(UsageError(format!(
+                                "--mlfq-levels must be from 1 to {}, got '{}'",
+                                MAX_LEVELS, value
+                            )))
+                        }
+                    };
+                }
+                "--boost-interval" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.feedback.boost_interval = match value.parse::<i32>() {
+                        Ok(interval) if interval > 0 => interval,
+                        _ => {
+                            return Err(UsageError(format!(
+                                "--boost-interval must be a positive number of cycles, got '{}'",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "--costs" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.costs = Self::parse_costs(&value)?;
+                }
+                "--runs" => {
+                    let value = Self::value_of(&arg, args.next())?;
+                    parsed.runs = Some(Self::parse_runs(&value)?);
+                }
+                _ if arg.starts_with("--") => {
+                    return Err(UsageError(format!("unknown option '{}'", arg)))
+                }
+                _ => {
+                    match positional {
+                        0 => parsed.param_file = arg,
+                        1 => parsed.input_file = arg,
+                        2 => {
+                            parsed.debug = match arg.to_uppercase().as_str() {
+                                "Y" => true,
+                                "N" => false,
+                                _ => {
+                                    return Err(UsageError(format!(
+                                        "debug flag must be Y or N, got '{}'",
+                                        arg
+                                    )))
+                                }
+                            }
+                        }
+                        _ => return Err(UsageError(format!("unexpected argument '{}'", arg))),
+                    }
+                    positional += 1;
+                }
+            }
+        }
+        Ok(Command::Run(parsed))
+    }
+
+    /** The value following an option */
+    fn value_of(option: &str, value: Option<String>) -> Result<String, UsageError> {
+        value.ok_or_else(|| UsageError(format!("{} needs a value", option)))
+    }
+
+    /** Parses a list of parameter record numbers and ranges, like "1,3-4"
+    @returns
+    the (first, last) record of each range in the order given, starting at 1.
+    A single record is a range of one */
+    fn parse_runs(value: &str) -> Result<Vec<(usize, usize)>, UsageError> {
+        let bad_run = || {
+            UsageError(format!(
+                "--runs takes record numbers starting at 1, like '1,3-4', got '{}'",
+                value
+            ))
+        };
+        let mut runs = Vec::new();
+        for part in value.split(',') {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (first, last),
+                None => (part, part),
+            };
+            let first: usize = first.trim().parse().map_err(|_| bad_run())?;
+            let last: usize = last.trim().parse().map_err(|_| bad_run())?;
+            if first == 0 || last < first {
+                return Err(bad_run());
+            }
+            runs.push((first, last));
+        }
+        Ok(runs)
+    }
+
+    /** Parses OS routine costs, like "dispatch=1,master=8". Routines not listed keep their
+    default cost. Every routine takes at least 1 cycle
+    @returns
+    the clock cycles each OS routine takes */
+    fn parse_costs(value: &str) -> Result<RoutineCosts, UsageError> {
+        let bad_costs = || {
+            UsageError(format!(
+                "--costs takes ROUTINE=CYCLES pairs of at least 1 cycle, like 'dispatch=1,master=8', got '{}'",
+                value
+            ))
+        };
+        let mut costs = RoutineCosts::default();
+        for part in value.split(',') {
+            let (routine, cycles) = part.split_once('=').ok_or_else(bad_costs)?;
+            let cycles: i32 = match cycles.trim().parse() {
+                Ok(cycles) if cycles > 0 => cycles,
+                _ => return Err(bad_costs()),
+            };
+            match routine.trim().to_lowercase().as_str() {
+                "dispatch" => costs.dispatch = cycles,
+                "ios" => costs.ios = cycles,
+                "dones" => costs.dones = cycles,
+                "master" => costs.master = cycles,
+                "compact" => costs.compact_per_kb = cycles,
+                _ => return Err(UsageError(format!("unknown --costs routine '{}'", routine))),
+            }
+        }
+        Ok(costs)
+    }
+
+    /** Checks if a parameter record, starting at 1, should be simulated */
+    pub fn selects(&self, sim_number: usize) -> bool {
+        self.runs.as_ref().is_none_or(|runs| {
+            runs.iter()
+                .any(|(first, last)| (*first..=*last).contains(&sim_number))
+        })
+    }
+}
+
+/** Usage of this program, as displayed by /? */
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: {} [PARAM_FILE] [INPUT_FILE] [Y|N] [OPTIONS]
+
+Simulates a simple Operating System, once for each O/S parameter record.
+
+Arguments:
+  PARAM_FILE             O/S parameter file (default {})
+  INPUT_FILE             input queue file (default {})
+  Y|N                    print the input data list before simulating (default Y)
+
+Files ending in .txt are read as text tables, .toml and .yaml/.yml as TOML and YAML;
+anything else is read as a binary .DAT file, legacy or extended.
+
+Options:
+  --clock-limit CYCLES   stop a runaway simulation after CYCLES clock units (default {})
+  --format full|brief    brief leaves out the detail printed every N units (default full)
+  --placement contiguous|scattered
+                         whether a process's fixed memory blocks must be one run of
+                         blocks, or can be any free blocks (default contiguous)
+  --fit first|best|worst|next|buddy
+                         how variable memory is given out: a hole for an exact sized
+                         partition, or a binary buddy system block (default first)
+  --compact PERCENT      compact variable memory when a process only fits once free
+                         memory is merged, and at least PERCENT of free memory is
+                         outside the largest hole (default never)
+  --mlfq-levels N        number of MLFQ levels, from 1 to {} (default {})
+  --boost-interval CYCLES
+                         move every MLFQ process back to the top level every CYCLES
+                         clock units (default {})
+  --costs ROUTINE=CYCLES,...
+                         clock cycles each OS routine takes: dispatch, ios, dones and
+                         master, and compact per KB moved (default dispatch=1,ios=3,
+                         dones=3,master=4,compact=1)
+  --runs LIST            only simulate these parameter records, like 1,3-4 (default all)
+  /?, -h, --help         display this usage",
+        program,
+        DEFAULT_PARAM_FILE,
+        DEFAULT_INPUT_FILE,
+        DEFAULT_CLOCK_LIMIT,
+        MAX_LEVELS,
+        DEFAULT_LEVELS,
+        DEFAULT_BOOST_INTERVAL
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, UsageError> {
+        match Args::parse(args.iter().map(|arg| arg.to_string()))? {
+            Command::Run(args) => Ok(args),
+            Command::Help => panic!("expected arguments to run with, not help"),
+        }
+    }
+
+    #[test]
+    fn positional_arguments_and_options_are_read_in_any_order() {
+        let args = parse(&["--runs", "2", "p.txt", "--clock-limit", "90", "i.toml", "n"]).unwrap();
+        assert_eq!(args.param_file, "p.txt");
+        assert_eq!(args.input_file, "i.toml");
+        assert!(!args.debug);
+        assert_eq!(args.clock_limit, 90);
+        assert_eq!(args.runs, Some(vec![(2, 2)]));
+
+        let args = parse(&[]).unwrap();
+        assert_eq!(args.param_file, DEFAULT_PARAM_FILE);
+        assert!(args.debug);
+        assert_eq!(args.runs, None);
+        assert!(matches!(
+            Args::parse(vec!["x".to_string(), "/?".to_string()]),
+            Ok(Command::Help)
+        ));
+    }
+
+    #[test]
+    fn bad_command_lines_say_what_is_wrong() {
+        for (args, message) in [
+            (&["--swap"][..], "unknown option '--swap'"),
+            (&["--runs"][..], "--runs needs a value"),
+            (
+                &["a", "b", "maybe"][..],
+                "debug flag must be Y or N, got 'maybe'",
+            ),
+            (&["a", "b", "Y", "c"][..], "unexpected argument 'c'"),
+            (
+                &["--clock-limit", "0"][..],
+                "--clock-limit must be a positive number of cycles, got '0'",
+            ),
+            (
+                &["--mlfq-levels", "17"][..],
+                "--mlfq-levels must be from 1 to 16, got '17'",
+            ),
+        ] {
+            assert_eq!(parse(args).unwrap_err().0, message);
+        }
+    }
+
+    #[test]
+    fn runs_are_kept_as_ranges() {
+        assert_eq!(
+            Args::parse_runs("1, 3-4,2").unwrap(),
+            vec![(1, 1), (3, 4), (2, 2)]
+        );
+        // a huge range is two numbers, not a list of every record in it
+        let args = parse(&["--runs", "5-99999999999"]).unwrap();
+        assert!(!args.selects(4));
+        assert!(args.selects(5));
+        assert!(args.selects(12345678));
+    }
+
+    #[test]
+    fn runs_must_be_ranges_of_records_from_1() {
+        for bad in ["0", "4-3", "1-", "-2", "a", "1,,2", "1-2-3", ""] {
+            assert!(Args::parse_runs(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn costs_override_only_the_routines_listed() {
+        let costs = Args::parse_costs("dispatch=2, MASTER=8,compact=5").unwrap();
+        assert_eq!(
+            (costs.dispatch, costs.ios, costs.dones, costs.master),
+            (2, 3, 3, 8)
+        );
+        assert_eq!(costs.compact_per_kb, 5);
+    }
+
+    #[test]
+    fn costs_must_be_known_routines_of_at_least_one_cycle() {
+        for bad in [
+            "dispatch=0",
+            "ios=-1",
+            "master",
+            "dones=x",
+            "compact=99999999999",
+            "",
+        ] {
+            assert!(Args::parse_costs(bad).is_err(), "{}", bad);
+        }
+        let err = Args::parse_costs("dispatch=1,swap=2").unwrap_err();
+        assert_eq!(err.0, "unknown --costs routine 'swap'");
+    }
+}
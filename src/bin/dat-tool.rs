@@ -0,0 +1,295 @@
+/*********************************************************/
+/*** dat-tool: creates and edits the binary .DAT files ***/
+/***===================================================***/
+/*** Lists, adds, changes and removes records in an    ***/
+/*** O/S parameter file (OS_OSP.DAT) or an input queue ***/
+/*** file (OS_INP.DAT). Records are numbered from 1,   ***/
+/*** as in the simulator's "Input Data List # N".      ***/
+/*** Also converts between the binary, text table,     ***/
+/*** TOML and YAML workload formats.                   ***/
+/*********************************************************/
+
+use os_project::records::{
+    Algorithm, IODeviceType, MemModel, OSParams, ProcessData, RecordError, RunInfo, MAX_SIZE,
+};
+use os_project::workload;
+use std::io::ErrorKind;
+use std::process;
+
+const USAGE: &str = "Usage: dat-tool params|input FILE list
+       dat-tool params|input FILE add [FIELD=VALUE ...]
+       dat-tool params|input FILE set N FIELD=VALUE ...
+       dat-tool params|input FILE remove N
+       dat-tool params|input FILE convert TO_FILE
+
+Records are numbered from 1. add and set write FILE back in place;
+add creates FILE if it doesn't exist. Files ending in .txt are text tables,
+.toml and .yaml/.yml are TOML and YAML, .xdat input files are extended binary
+records with no limit on run info rows, anything else is a binary .DAT file;
+convert copies every record from FILE to TO_FILE in TO_FILE's format.
+
+params fields:
+  mem_model=none|fixed|variable|paged  algorithm=FIFO|IPRI|MLFQ
+  block_size  total_blocks  var_maxsize  var_totsize  max_tasks
+  quantum  disk  tape  cd  every_n
+
+input fields:
+  name (at most 7 characters)  priority  memsize
+  run=CPU,IO,DEVICE  one per CPU burst, in order; DEVICE is 1=disk 2=tape 3=cd,
+                     or 0 when there is no IO. Replaces the record's run info";
+
+/** Record types dat-tool can edit */
+trait Record: Sized + std::fmt::Display {
+    /** Most records the simulator reads from one file, if there is a limit */
+    const MAX_RECORDS: Option<usize>;
+    fn read_all(filename: &str) -> Result<Vec<Self>, RecordError>;
+    fn write_all(filename: &str, records: &[Self]) -> Result<(), RecordError>;
+    fn blank() -> Self;
+    /** Applies FIELD=VALUE settings, in order */
+    fn set_fields(&mut self, fields: &[String]) -> Result<(), String>;
+}
+
+impl Record for OSParams {
+    const MAX_RECORDS: Option<usize> = None;
+    fn read_all(filename: &str) -> Result<Vec<Self>, RecordError> {
+        workload::read_params(filename)
+    }
+    fn write_all(filename: &str, records: &[Self]) -> Result<(), RecordError> {
+        workload::write_params(filename, records)
+    }
+    fn blank() -> Self {
+        OSParams {
+            mem_model: MemModel::None,
+            pro_algorithm: Algorithm::FIFO,
+            mem_fix_block_size: 0,
+            mem_fix_total_blocks: 0,
+            mem_var_maxsize: 0,
+            mem_var_totsize: 0,
+            pro_max_tasks: 0,
+            init_quantum: 0,
+            disk_units: 0,
+            tape_units: 0,
+            cdrom_units: 0,
+            every_n_units: 0,
+        }
+    }
+    fn set_fields(&mut self, fields: &[String]) -> Result<(), String> {
+        for setting in fields {
+            let (field, value) = split_setting(setting)?;
+            match field {
+                "mem_model" => self.mem_model = value.parse()?,
+                "algorithm" => self.pro_algorithm = value.parse()?,
+                "block_size" => self.mem_fix_block_size = parse_int(field, value)?,
+                "total_blocks" => self.mem_fix_total_blocks = parse_int(field, value)?,
+                "var_maxsize" => self.mem_var_maxsize = parse_int(field, value)?,
+                "var_totsize" => self.mem_var_totsize = parse_int(field, value)?,
+                "max_tasks" => self.pro_max_tasks = parse_int(field, value)?,
+                "quantum" => self.init_quantum = parse_int(field, value)?,
+                "disk" => self.disk_units = parse_int(field, value)?,
+                "tape" => self.tape_units = parse_int(field, value)?,
+                "cd" => self.cdrom_units = parse_int(field, value)?,
+                "every_n" => self.every_n_units = parse_int(field, value)?,
+                _ => return Err(format!("unknown params field '{}'", field)),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Record for ProcessData {
+    const MAX_RECORDS: Option<usize> = Some(MAX_SIZE);
+    fn read_all(filename: &str) -> Result<Vec<Self>, RecordError> {
+        workload::read_processes(filename)
+    }
+    fn write_all(filename: &str, records: &[Self]) -> Result<(), RecordError> {
+        workload::write_processes(filename, records)
+    }
+    fn blank() -> Self {
+        ProcessData {
+            process_priority: 0,
+            process_memsize: 0,
+            run_info: Vec::new(),
+            process_name: String::new(),
+        }
+    }
+    fn set_fields(&mut self, fields: &[String]) -> Result<(), String> {
+        let mut run_info = Vec::new();
+        for setting in fields {
+            let (field, value) = split_setting(setting)?;
+            match field {
+                "name" => self.process_name = value.to_string(),
+                "priority" => self.process_priority = parse_int(field, value)?,
+                "memsize" => self.process_memsize = parse_int(field, value)?,
+                "run" => run_info.push(parse_run(value)?),
+                _ => return Err(format!("unknown input field '{}'", field)),
+            }
+        }
+        if !run_info.is_empty() {
+            self.run_info = run_info;
+        }
+        Ok(())
+    }
+}
+
+fn split_setting(setting: &str) -> Result<(&str, &str), String> {
+    setting
+        .split_once('=')
+        .ok_or_else(|| format!("expected FIELD=VALUE, got '{}'", setting))
+}
+
+fn parse_int(field: &str, value: &str) -> Result<i32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a number, got '{}'", field, value))
+}
+
+/** Parses one CPU burst as "CPU,IO,DEVICE" */
+fn parse_run(value: &str) -> Result<RunInfo, String> {
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    if parts.len() != 3 {
+        return Err(format!("run must be CPU,IO,DEVICE, got '{}'", value));
+    }
+    let device = parse_int("run device", parts[2])?;
+    Ok(RunInfo {
+        CPU_units: parse_int("run CPU units", parts[0])?,
+        IO_units: parse_int("run IO units", parts[1])?,
+        IO_device_type: IODeviceType::from_code(device)
+            .ok_or_else(|| format!("unknown device type '{}'", device))?,
+    })
+}
+
+/** Parses a record number, starting at 1, of an existing record
+@returns
+the index of the record in the file */
+fn record_index(number: Option<&String>, len: usize) -> Result<usize, String> {
+    let number = number.ok_or("missing record number N")?;
+    match number.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= len => Ok(n - 1),
+        _ => Err(format!(
+            "record number must be from 1 to {}, got '{}'",
+            len, number
+        )),
+    }
+}
+
+/** Writes records to a file, unless there are more than the simulator would read back */
+fn write_records<R: Record>(filename: &str, records: &[R]) -> Result<(), String> {
+    let result = match R::MAX_RECORDS {
+        Some(max) if records.len() > max => Err(RecordError::TooManyRecords {
+            count: records.len(),
+            max,
+        }),
+        _ => R::write_all(filename, records),
+    };
+    result.map_err(|e| format!("can't write {}: {}", filename, e))
+}
+
+/** Runs one dat-tool command against a file of records */
+fn run<R: Record>(filename: &str, command: &str, rest: &[String]) -> Result<(), String> {
+    let read = |missing_ok: bool| match R::read_all(filename) {
+        Err(RecordError::Io(ref e)) if missing_ok && e.kind() == ErrorKind::NotFound => {
+            Ok(Vec::new())
+        }
+        result => result.map_err(|e| format!("can't read {}: {}", filename, e)),
+    };
+    let write = |records: &[R]| write_records(filename, records);
+
+    match command {
+        "list" => {
+            for (idx, record) in read(false)?.iter().enumerate() {
+                println!("Record # {}:", idx + 1);
+                println!("{}", record);
+            }
+        }
+        "add" => {
+            let mut records = read(true)?;
+            let mut record = R::blank();
+            record.set_fields(rest)?;
+            records.push(record);
+            write(&records)?;
+            println!("Added record # {} to {}", records.len(), filename);
+        }
+        "set" => {
+            let mut records = read(false)?;
+            let idx = record_index(rest.first(), records.len())?;
+            records[idx].set_fields(&rest[1..])?;
+            write(&records)?;
+            println!("Changed record # {} in {}", idx + 1, filename);
+        }
+        "remove" => {
+            let mut records = read(false)?;
+            let idx = record_index(rest.first(), records.len())?;
+            records.remove(idx);
+            write(&records)?;
+            println!("Removed record # {} from {}", idx + 1, filename);
+        }
+        "convert" => {
+            let to_file = rest.first().ok_or("missing TO_FILE to convert to")?;
+            let records = read(false)?;
+            write_records(to_file, &records)?;
+            println!(
+                "Converted {} records from {} to {}",
+                records.len(),
+                filename,
+                to_file
+            );
+        }
+        _ => return Err(format!("unknown command '{}'", command)),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "/?" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    if args.len() < 3 {
+        eprintln!("Error: expected a file type, FILE and command\n\n{}", USAGE);
+        process::exit(1);
+    }
+    let result = match args[0].as_str() {
+        "params" => run::<OSParams>(&args[1], &args[2], &args[3..]),
+        "input" => run::<ProcessData>(&args[1], &args[2], &args[3..]),
+        other => Err(format!(
+            "file type must be params or input, got '{}'",
+            other
+        )),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}\n\n{}", e, USAGE);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn add_stops_at_max_size_input_records() {
+        let filename = env::temp_dir()
+            .join(format!("dat-tool-{}-many.DAT", process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let fields = vec!["name=MANY".to_string(), "memsize=10".to_string()];
+        for _ in 0..MAX_SIZE {
+            run::<ProcessData>(&filename, "add", &fields).unwrap();
+        }
+        let result = run::<ProcessData>(&filename, "add", &fields);
+        let records = ProcessData::read_all(&filename);
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(
+            result.unwrap_err(),
+            format!(
+                "can't write {}: file holds 33 records, but at most 32 are allowed",
+                filename
+            )
+        );
+        assert_eq!(records.unwrap().len(), MAX_SIZE);
+    }
+}
@@ -0,0 +1,14 @@
+/*********************************************************/
+/*** OS_SYS library: the O/S simulator and the .DAT    ***/
+/*** record formats, shared by the simulator and the   ***/
+/*** dat-tool editor.                                  ***/
+/*********************************************************/
+
+#![allow(non_snake_case)]
+#![allow(clippy::upper_case_acronyms)]
+#![allow(clippy::module_inception)]
+
+pub mod os;
+pub mod records;
+pub mod validate;
+pub mod workload;
@@ -5,6 +5,7 @@
 /*** Input Files:                                      ***/
 /***   1) O/S Parameter File: OS_OSP.DAT               ***/
 /***   2) Input Queue Data: OS_INP.DAT                 ***/
+/***   Either may be a .txt table, .toml or .yaml file ***/
 /*** Output Files:                                     ***/
 /***   1) Print O/S Start-End times and Parameter data.***/
 /***   2) Print Process Allocation... De-allocation    ***/
@@ -13,32 +14,136 @@
 /***       detail information each N units.            ***/
 /*********************************************************/
 
-#![allow(non_snake_case)]
-#![allow(dead_code)]
+mod args;
 
-mod os;
-mod records;
+use os_project::{os, records, validate, workload};
 
-use os::os::OS;
+use args::{Args, Command, OutputFormat};
+use os::os::{OS, VERSION};
 use records::{OSParams, ProcessData};
+use std::process;
 
 fn main() {
-    let params = open_params();
-    let all_records = open_records();
+    let mut argv = std::env::args();
+    let program = argv.next().unwrap_or_else(|| "OS-Project".to_string());
+    let args = match Args::parse(argv) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", args::usage(&program));
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, args::usage(&program));
+            process::exit(1);
+        }
+    };
 
-    let mut os = OS::new(params, all_records, 5000);
-    os.start();
+    let all_params = open_params(&args.param_file);
+    let all_records = open_records(&args.input_file);
+
+    if let Some(runs) = &args.runs {
+        if let Some((_, missing)) = runs.iter().find(|(_, last)| *last > all_params.len()) {
+            eprintln!(
+                "Error: no O/S parameter record {} in {}; it has {} records",
+                missing,
+                args.param_file,
+                all_params.len()
+            );
+            process::exit(1);
+        }
+    }
+
+    if !check_workload(&args, &all_params, &all_records) {
+        eprintln!("Error: the workload has errors; not simulating");
+        process::exit(1);
+    }
+    if args.debug {
+        print_input_data(&all_records);
+    }
+
+    // each O/S parameter record is a separate simulation run with fresh state
+    for (idx, params) in all_params.into_iter().enumerate() {
+        let sim_number = idx + 1;
+        if !args.selects(sim_number) {
+            continue;
+        }
+        println!(
+            "\nStarting O/S Simulation: {}. Version {}\n",
+            sim_number,
+            VERSION.unwrap_or("(unknown)")
+        );
+
+        let mut os = match OS::new(
+            params,
+            all_records.clone(),
+            args.clock_limit,
+            args.memory,
+            args.feedback,
+            args.costs,
+        ) {
+            Ok(os) => os,
+            Err(e) => {
+                eprintln!("Skipping O/S Simulation {}: {}", sim_number, e);
+                continue;
+            }
+        };
+        os.print_detail = args.format == OutputFormat::Full;
+        os.start();
+
+        println!(
+            "Ended O/S Simulation: {}. Last clock: {}",
+            sim_number, os.master_clock
+        );
+    }
+}
+
+/** Validates the parameter records that will be simulated and every input record,
+printing each problem found
+@returns
+false if there were any errors */
+fn check_workload(args: &Args, params: &[OSParams], records: &[ProcessData]) -> bool {
+    let mut ok = true;
+    let mut report = |what: String, problems: Vec<validate::Problem>| {
+        ok &= !validate::has_errors(&problems);
+        for problem in problems {
+            eprintln!("{}: {}", what, problem);
+        }
+    };
+    for (idx, param) in params.iter().enumerate() {
+        if args.selects(idx + 1) {
+            let mut problems = param.validate();
+            problems.extend(param.validate_workload(records));
+            report(format!("O/S parameter record {}", idx + 1), problems);
+        }
+    }
+    for (idx, record) in records.iter().enumerate() {
+        report(format!("Input Data List # {}", idx + 1), record.validate());
+    }
+    ok
+}
+
+fn print_input_data(records: &[ProcessData]) {
+    for (idx, record) in records.iter().enumerate() {
+        println!("\nInput Data List # {} Contains:", idx + 1);
+        print!("{}", record);
+    }
 }
 
-fn open_params() -> OSParams {
-    match OSParams::read_from_file("./res/OS_OSP.DAT") {
+fn open_params(filename: &str) -> Vec<OSParams> {
+    match workload::read_params(filename) {
         Ok(t) => t,
-        Err(e) => panic!("{}", e),
+        Err(e) => {
+            eprintln!("Error: can't read O/S parameter file {}: {}", filename, e);
+            process::exit(1);
+        }
     }
 }
-fn open_records() -> Vec<ProcessData> {
-    match ProcessData::read_from_file("./res/OS_INP.DAT", 10) {
+fn open_records(filename: &str) -> Vec<ProcessData> {
+    match workload::read_processes(filename) {
         Ok(t) => t,
-        Err(e) => panic!("{}", e),
+        Err(e) => {
+            eprintln!("Error: can't read input queue file {}: {}", filename, e);
+            process::exit(1);
+        }
     }
 }
@@ -1,75 +1,107 @@
-use self::AllocResult::*;
-use crate::os::memory::MemoryRange;
+use crate::os::memory::AllocResult::{self, *};
 use crate::os::os::OS;
 use crate::os::process::{ProcessControlBlock, ProcessState};
 use crate::records::ProcessData;
-
-use std::collections::HashSet;
-
-// result of allocation attempt
-enum AllocResult {
-    NoSpace,
-    TooBig,
-    Allocated(MemoryRange),
-}
+use itertools::sorted;
 
 pub struct Allocator;
 impl Allocator {
-    /** Allocates processes when there is room */
+    /** MASTER: allocates processes from the input queue while there are free PCB spots.
+    Every process in the queue gets one try; the ones that don't fit yet stay where they were
+    in the queue, so FIFO and IPRI order are kept
+    @returns
+    true if compacting memory would let a process that didn't fit be allocated */
     pub fn allocate(os: &mut OS) -> bool {
-        let mut cycled = false; // did the OS use up a clock cycle by allocating, or was input queue empty?
-        loop {
-            // allocate until the queue is empty
-            if let Some(info) = os.input_queue.pop_front() {
-                // try to allocate, and check result of allocation
-                match Self::alloc_one(os, &info) {
-                    // everything was ok, process allocated
-                    Allocated(_) => {
-                        println!(
-                            "Allocated {} at clock time {}",
-                            info.process_name, os.master_clock
-                        );
-                        cycled = true;
-                    }
+        let mut needs_compaction = false;
+        let mut idx = 0;
+        while idx < os.input_queue.len() {
+            let max_tasks = os.input_params.pro_max_tasks;
+            if max_tasks > 0 && os.running_processes.len() as i32 >= max_tasks {
+                break;
+            }
+            let info = match os.input_queue.remove(idx) {
+                Some(info) => info,
+                None => break,
+            };
+            // try to allocate, and check result of allocation
+            match Self::alloc_one(os, &info) {
+                // everything was ok, process allocated
+                Allocated(_) => {
+                    println!(
+                        "Allocated {} at clock time {}",
+                        info.process_name, os.master_clock
+                    );
+                }
 
-                    // process too big. don't re add to queue
-                    TooBig => {
-                        println!(
-                            "Flushed {} from input queue: Not enough memory!",
-                            info.process_name
-                        );
-                        break;
-                    }
+                // process too big. don't re add to queue
+                TooBig => {
+                    println!(
+                        "Flushed {} from input queue: Not enough memory!",
+                        info.process_name
+                    );
+                }
 
-                    // no space this time, try to add next clock cycle.
-                    NoSpace => {
-                        os.input_queue.push_back(info);
-                        break;
-                    }
+                // no space this time, try again once memory is freed or compacted.
+                NoSpace => {
+                    needs_compaction |= os.memory.compaction_helps(&info);
+                    os.input_queue.insert(idx, info);
+                    idx += 1;
                 }
-            } else {
-                break;
             }
         }
-        cycled
+        needs_compaction
+    }
+
+    /** COMPACT: moves resident processes together to merge free memory,
+    and logs the free memory before and after */
+    pub fn compact(os: &mut OS) {
+        let before = os.memory.to_string();
+        let old_map = os.memory_map.clone();
+        let moved = os.memory.compact(&mut os.memory_map);
+        println!(
+            "Compacted memory at MC time: {}  ({} bytes moved)",
+            os.master_clock, moved
+        );
+        for line in before.lines() {
+            println!("   Before: {}", line);
+        }
+        for line in os.memory.to_string().lines() {
+            println!("   After:  {}", line);
+        }
+        for pid in sorted(os.memory_map.keys()) {
+            let range = &os.memory_map[pid];
+            let old = old_map[pid].to_string();
+            if old != range.to_string() {
+                println!(
+                    "   Moved PID#: {} from {} to {}",
+                    pid,
+                    old.trim_end(),
+                    range.to_string().trim_end()
+                );
+            }
+            if let Some(proc) = os.running_processes.get_mut(pid) {
+                proc.memory_map = range.clone();
+            }
+        }
+        println!();
     }
+
     /** Allocates a single process
     @returns:
         false if there is no room for the process in memory
         true if allocation succeeded. */
     fn alloc_one(os: &mut OS, info: &ProcessData) -> AllocResult {
-        let memory_range = match Self::check_memory(os, info) {
+        let pid = os.input_size - os.input_queue.len() as i32;
+
+        // the memory manager for the OS's memory model checks for, and reserves, memory
+        let memory_range = match os.memory.allocate(pid, info) {
             Allocated(T) => T,
             NoSpace => return NoSpace,
             TooBig => return TooBig,
         };
 
-        let pid = os.input_size - os.input_queue.len() as i32;
-
         // add process to memory map
         os.memory_map.insert(pid, memory_range.clone());
-        // add pid to FIFO scheduling queue
-        os.ready_queue.push_back(pid);
         // add
         os.running_processes.insert(
             pid,
@@ -80,50 +112,53 @@ impl Allocator {
                 state: ProcessState::Allocating,
                 total_cpu: 0,
                 total_ios: 0,
+                context_switches: 0,
                 start_time: 0,
                 end_time: 0,
                 memory_map: memory_range.clone(),
             },
         );
+        // add pid to the ready list, in FIFO or priority order
+        os.set_ready(pid);
         Allocated(memory_range)
     }
+}
 
-    /** Checks if memory is available for a given process, and returns the available memory range if it is */
-    fn check_memory(os: &mut OS, info: &ProcessData) -> AllocResult {
-        let proc_mem_size = info.process_memsize / (os.input_params.mem_fix_block_size / 1000);
-        let os_mem_max = os.input_params.mem_fix_total_blocks;
-
-        // check if this process will even fit in our total memory
-        if proc_mem_size > os_mem_max {
-            return TooBig;
-        }
-        // if nothing is already in memory, we get the first available bytes
-        if os.memory_map.is_empty() {
-            return Allocated(MemoryRange(1, proc_mem_size));
-        }
-
-        // otherwise, check each process's memory range to see if we can fit somewhere
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os::interrupts::RoutineCosts;
+    use crate::os::memory::MemoryOptions;
+    use crate::os::mlfq::FeedbackOptions;
+    use crate::records::OSParams;
 
-        // fill up vec with all currently allocated memory blocks
-        let mut allocated_blocks = HashSet::with_capacity(os_mem_max as usize);
-        for range in os.memory_map.values() {
-            for i in range.0..=range.1 {
-                allocated_blocks.insert(i);
-            }
+    #[test]
+    fn processes_that_dont_fit_keep_their_place_in_the_input_queue() {
+        // 10 blocks of 1 KB, and room for 2 processes
+        let mut params = OSParams::with_fixed_blocks(1000, 10);
+        params.pro_max_tasks = 2;
+        let mut os = OS::new(
+            params,
+            Vec::new(),
+            1000,
+            MemoryOptions::default(),
+            FeedbackOptions::default(),
+            RoutineCosts::default(),
+        )
+        .unwrap();
+        for memsize in [6, 6, 2, 3] {
+            os.input_queue.push_back(ProcessData::with_memsize(memsize));
         }
+        os.input_size = os.input_queue.len() as i32;
 
-        // check each OS memory block
-        let mut prev = 0;
-        for block in 0..=os_mem_max {
-            if block - prev >= proc_mem_size {
-                // we found room for our new process!
-                return Allocated(MemoryRange(block - proc_mem_size + 1, block));
-            }
-            if allocated_blocks.contains(&block) {
-                prev = block;
-            }
-        }
-        // didn't find any room
-        NoSpace
+        // the second 6 KB process doesn't fit, then the task limit stops the pass after 2 KB
+        Allocator::allocate(&mut os);
+        let waiting: Vec<i32> = os
+            .input_queue
+            .iter()
+            .map(|info| info.process_memsize)
+            .collect();
+        assert_eq!(waiting, vec![6, 3]);
+        assert_eq!(os.running_processes.len(), 2);
     }
 }
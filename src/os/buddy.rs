@@ -0,0 +1,259 @@
+use crate::os::memory::AllocResult::{self, *};
+use crate::os::memory::{MemoryManager, MemoryRange};
+use crate::os::process::PID;
+use crate::records::ProcessData;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/** Size in bytes of the smallest block the buddy system hands out */
+pub const MIN_BLOCK_SIZE: i32 = 1024;
+/** Size in bytes of the largest block: the largest power of two in an i32 */
+pub const MAX_BLOCK_SIZE: i32 = 1 << 30;
+
+/** A block given to a process */
+#[derive(Debug, Copy, Clone)]
+struct UsedBlock {
+    order: usize,   // the block is MIN_BLOCK_SIZE << order bytes
+    pid: PID,       // process the block was given to
+    requested: i32, // bytes the process asked for
+}
+
+/** Binary buddy system memory. Blocks are powers of two in size; a block is split in
+half to make smaller blocks, and freed blocks merge with their buddy when it is free too.
+Memory is one block, so it is cut to MAX_BLOCK_SIZE if it is any bigger */
+pub struct BuddySystem {
+    pub total_size: i32,            // mem_var_totsize, rounded up to a power of two
+    pub max_size: i32,              // largest request a single process may make
+    free_lists: Vec<BTreeSet<i32>>, // first byte of each free block, by order
+    used: BTreeMap<i32, UsedBlock>, // blocks given to processes, by first byte
+}
+
+impl BuddySystem {
+    pub fn new(total_size: i32, max_size: i32) -> Self {
+        let total_size = if total_size > 0 {
+            (total_size.clamp(MIN_BLOCK_SIZE, MAX_BLOCK_SIZE) as u32).next_power_of_two() as i32
+        } else {
+            0
+        };
+        let mut free_lists = vec![BTreeSet::new(); Self::order_of(total_size) + 1];
+        if total_size > 0 {
+            free_lists[Self::order_of(total_size)].insert(0);
+        }
+        Self {
+            total_size,
+            max_size,
+            free_lists,
+            used: BTreeMap::new(),
+        }
+    }
+
+    /** The order of the smallest block that holds `size` bytes, up to MAX_BLOCK_SIZE */
+    fn order_of(size: i32) -> usize {
+        let mut order = 0;
+        while MIN_BLOCK_SIZE << order < size.min(MAX_BLOCK_SIZE) {
+            order += 1;
+        }
+        order
+    }
+
+    fn block_size(order: usize) -> i32 {
+        MIN_BLOCK_SIZE << order
+    }
+
+    /** Takes the lowest addressed free block of an order, splitting a larger block if needed
+    @returns
+    Some(first byte) of the block
+    None if there is no free block that big */
+    fn take_block(&mut self, order: usize) -> Option<i32> {
+        let from = (order..self.free_lists.len()).find(|o| !self.free_lists[*o].is_empty())?;
+        let start = self.free_lists[from].pop_first()?;
+        // split down to the requested order, keeping the lower half each time
+        for lower in (order..from).rev() {
+            self.free_lists[lower].insert(start + Self::block_size(lower));
+        }
+        Some(start)
+    }
+
+    /** Returns a block to its free list, merging it with its buddy for as long as the buddy is free */
+    fn give_back(&mut self, mut start: i32, mut order: usize) {
+        while order + 1 < self.free_lists.len() {
+            let buddy = start ^ Self::block_size(order);
+            if !self.free_lists[order].remove(&buddy) {
+                break;
+            }
+            start = start.min(buddy);
+            order += 1;
+        }
+        self.free_lists[order].insert(start);
+    }
+
+    /** Bytes given to processes but not asked for, in total */
+    pub fn internal_fragmentation(&self) -> i32 {
+        self.used
+            .values()
+            .map(|block| Self::block_size(block.order) - block.requested)
+            .sum()
+    }
+
+    /** Writes the buddy tree under a block, one line per block, indented by depth */
+    fn write_tree(
+        &self,
+        f: &mut fmt::Formatter,
+        start: i32,
+        order: usize,
+        depth: usize,
+    ) -> fmt::Result {
+        let size = Self::block_size(order);
+        let indent = "  ".repeat(depth);
+        let range = format!("{}-{}", start, start + size - 1);
+        if self.free_lists[order].contains(&start) {
+            return writeln!(f, "{}{} free", indent, range);
+        }
+        match self.used.get(&start) {
+            Some(block) if block.order == order => writeln!(
+                f,
+                "{}{} PID#: {}  {} of {} bytes used, {} wasted",
+                indent,
+                range,
+                block.pid,
+                block.requested,
+                size,
+                size - block.requested
+            ),
+            _ if order == 0 => writeln!(f, "{}{} ?", indent, range),
+            _ => {
+                writeln!(f, "{}{} split", indent, range)?;
+                self.write_tree(f, start, order - 1, depth + 1)?;
+                self.write_tree(f, start + size / 2, order - 1, depth + 1)
+            }
+        }
+    }
+}
+
+impl MemoryManager for BuddySystem {
+    /** Gives a process the smallest free power of two block that holds it.
+    Process memory sizes are in KB, blocks are in bytes */
+    fn allocate(&mut self, pid: PID, info: &ProcessData) -> AllocResult {
+        // check if this process will ever fit in a block
+        let requested = match info.memsize_bytes() {
+            Some(size)
+                if size <= self.total_size && (self.max_size <= 0 || size <= self.max_size) =>
+            {
+                size
+            }
+            _ => return TooBig,
+        };
+        let order = Self::order_of(requested);
+        match self.take_block(order) {
+            Some(start) => {
+                self.used.insert(
+                    start,
+                    UsedBlock {
+                        order,
+                        pid,
+                        requested,
+                    },
+                );
+                Allocated(MemoryRange::Bytes(
+                    start,
+                    start + Self::block_size(order) - 1,
+                ))
+            }
+            None => NoSpace,
+        }
+    }
+
+    fn free(&mut self, _pid: PID, range: &MemoryRange) {
+        if let MemoryRange::Bytes(first, _) = range {
+            if let Some(block) = self.used.remove(first) {
+                self.give_back(*first, block.order);
+            }
+        }
+    }
+}
+
+impl fmt::Display for BuddySystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Buddy blocks ({} bytes):", self.total_size)?;
+        if self.total_size > 0 {
+            self.write_tree(f, 0, self.free_lists.len() - 1, 1)?;
+        }
+        let given: i32 = self
+            .used
+            .values()
+            .map(|block| Self::block_size(block.order))
+            .sum();
+        let wasted = self.internal_fragmentation();
+        let percent = if given > 0 {
+            100.0 * f64::from(wasted) / f64::from(given)
+        } else {
+            0.0
+        };
+        writeln!(
+            f,
+            "Internal fragmentation: {} of {} bytes in use are wasted ({:.1}%)",
+            wasted, given, percent
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_is_rounded_up_to_a_power_of_two() {
+        assert_eq!(BuddySystem::new(100000, 0).total_size, 131072);
+        assert_eq!(BuddySystem::new(65536, 0).total_size, 65536);
+        assert_eq!(BuddySystem::new(0, 0).total_size, 0);
+    }
+
+    #[test]
+    fn memory_over_2_30_bytes_is_cut_to_the_largest_block() {
+        let mut memory = BuddySystem::new(i32::MAX, 0);
+        assert_eq!(memory.total_size, MAX_BLOCK_SIZE);
+        assert_eq!(BuddySystem::order_of(i32::MAX), 20);
+        assert!(matches!(
+            memory.allocate(1, &ProcessData::with_memsize(2000000)),
+            TooBig
+        ));
+        assert!(matches!(
+            memory.allocate(1, &ProcessData::with_memsize(1000000)),
+            Allocated(MemoryRange::Bytes(0, 1073741823))
+        ));
+    }
+
+    #[test]
+    fn blocks_split_and_coalesce() {
+        let mut memory = BuddySystem::new(100000, 0);
+        let a = memory.allocate(1, &ProcessData::with_memsize(30));
+        assert!(matches!(a, Allocated(MemoryRange::Bytes(0, 32767))));
+        let b = memory.allocate(2, &ProcessData::with_memsize(10));
+        assert!(matches!(b, Allocated(MemoryRange::Bytes(32768, 49151))));
+        assert_eq!(
+            memory.internal_fragmentation(),
+            (32768 - 30000) + (16384 - 10000)
+        );
+        // 64K is the biggest free block left
+        assert!(matches!(
+            memory.allocate(3, &ProcessData::with_memsize(70)),
+            NoSpace
+        ));
+        assert!(matches!(
+            memory.allocate(3, &ProcessData::with_memsize(140)),
+            TooBig
+        ));
+
+        for (pid, range) in [(1, a), (2, b)] {
+            if let Allocated(range) = range {
+                memory.free(pid, &range);
+            }
+        }
+        // everything merged back into one block
+        assert_eq!(memory.internal_fragmentation(), 0);
+        assert!(matches!(
+            memory.allocate(4, &ProcessData::with_memsize(100)),
+            Allocated(MemoryRange::Bytes(0, 131071))
+        ));
+    }
+}
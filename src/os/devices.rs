@@ -0,0 +1,135 @@
+use crate::os::process::PID;
+use crate::records::{IODeviceType, OSParams};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeviceState {
+    Avail,
+    Busy,
+}
+
+/** A single IO device unit */
+pub struct Device {
+    pub did: i32,         // device ID, unique within its type. Starts at 1
+    pub pid: Option<PID>, // process currently using this device
+    pub state: DeviceState,
+}
+
+/** Every IO device unit in the system, by type, with a queue of processes waiting on each type */
+pub struct DeviceTable {
+    devices: HashMap<IODeviceType, Vec<Device>>,
+    pending: HashMap<IODeviceType, VecDeque<PID>>,
+}
+
+impl DeviceTable {
+    pub fn new(params: &OSParams) -> Self {
+        let units = [
+            (IODeviceType::Disk, params.disk_units),
+            (IODeviceType::Tape, params.tape_units),
+            (IODeviceType::CD, params.cdrom_units),
+        ];
+        let mut devices = HashMap::with_capacity(units.len());
+        for (dtype, num_units) in units.iter() {
+            let unit_list = (1..=*num_units)
+                .map(|did| Device {
+                    did,
+                    pid: None,
+                    state: DeviceState::Avail,
+                })
+                .collect();
+            devices.insert(*dtype, unit_list);
+        }
+        Self {
+            devices,
+            pending: HashMap::new(),
+        }
+    }
+
+    /** Requests a device of the given type for a process.
+    @returns
+    Some(device ID) if a unit was available and is now busy with this process
+    None if every unit is busy; the process waits in the pending queue for the type */
+    pub fn request(&mut self, dtype: IODeviceType, pid: PID) -> Option<i32> {
+        let free_unit = self.devices.get_mut(&dtype).and_then(|units| {
+            units
+                .iter_mut()
+                .find(|unit| unit.state == DeviceState::Avail)
+        });
+        match free_unit {
+            Some(unit) => {
+                unit.state = DeviceState::Busy;
+                unit.pid = Some(pid);
+                Some(unit.did)
+            }
+            None => {
+                self.pending.entry(dtype).or_default().push_back(pid);
+                None
+            }
+        }
+    }
+
+    /** Makes the device a process was using available again. If a process is pending
+    on that device type, the unit goes straight to it.
+    @returns
+    Some(PID) of the pending process that was given the device */
+    pub fn release(&mut self, pid: PID) -> Option<PID> {
+        let (dtype, did) = self.device_of(pid)?;
+        let unit = self
+            .devices
+            .get_mut(&dtype)?
+            .iter_mut()
+            .find(|unit| unit.did == did)?;
+        match self
+            .pending
+            .get_mut(&dtype)
+            .and_then(|queue| queue.pop_front())
+        {
+            Some(next_pid) => {
+                unit.pid = Some(next_pid);
+                Some(next_pid)
+            }
+            None => {
+                unit.pid = None;
+                unit.state = DeviceState::Avail;
+                None
+            }
+        }
+    }
+
+    /** Removes a process from every device and pending queue, e.g. when it is removed from the OS */
+    pub fn remove(&mut self, pid: PID) {
+        if self.device_of(pid).is_some() {
+            self.release(pid);
+        }
+        for queue in self.pending.values_mut() {
+            queue.retain(|other| *other != pid);
+        }
+    }
+
+    /** The (type, device ID) of the device a process is using, if any */
+    pub fn device_of(&self, pid: PID) -> Option<(IODeviceType, i32)> {
+        self.devices.iter().find_map(|(dtype, units)| {
+            units
+                .iter()
+                .find(|unit| unit.pid == Some(pid))
+                .map(|unit| (*dtype, unit.did))
+        })
+    }
+
+    /** Checks if a process is waiting for a device to become available */
+    pub fn is_pending(&self, pid: PID) -> bool {
+        self.pending.values().any(|queue| queue.contains(&pid))
+    }
+
+    /** IO status of a process as "T-Dv#" (device type code - device ID), "PENDG" if it is
+    waiting for a device, or empty if it has no IO request */
+    pub fn status_of(&self, pid: PID) -> String {
+        if let Some((dtype, did)) = self.device_of(pid) {
+            format!("{}-{}", dtype.code(), did)
+        } else if self.is_pending(pid) {
+            String::from("PENDG")
+        } else {
+            String::new()
+        }
+    }
+}
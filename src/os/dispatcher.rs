@@ -1,30 +1,51 @@
+use crate::os::interrupts::{Interrupt, InterruptController};
 use crate::os::os::OS;
-use crate::os::process::{ProcessControlBlock, ProcessState, PID};
-use crate::records::IODeviceType;
-use std::collections::{HashMap, VecDeque};
+use crate::os::process::{ProcessState, PID};
+use crate::records::{Algorithm, IODeviceType};
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
 
-/** A dispatcher event -- IO completion, timeout, start or finish
-    .time: time at event creation
+/** A dispatcher event -- IO completion or timeout
+    .time: clock time the event fires at
     .pid: pid of process that created event
+    .seq: order the event was created in, so events at the same time fire first-in first-out
 */
 struct Event {
     pub _type: EventType,
     pub time: i32,
     pub pid: PID,
+    seq: u64,
 }
+#[derive(Eq, PartialEq)]
 enum EventType {
-    IO,       // IO completion
-    Timeout,  // CPU quantum completion
-    Started,  // process started
-    Finished, // process finished
+    IO,      // IO completion
+    Timeout, // CPU quantum completion
+}
+
+impl Ord for Event {
+    fn cmp(&self, other: &Event) -> Ordering {
+        (self.time, self.seq).cmp(&(other.time, other.seq))
+    }
+}
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Event) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Eq for Event {}
+impl PartialEq for Event {
+    fn eq(&self, other: &Event) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
 }
 
 pub struct Dispatcher {
     cpus_to_go: HashMap<PID, i32>,
     ios_to_go: HashMap<PID, (IODeviceType, i32)>,
     current_process: Option<PID>,
-    event_queue: VecDeque<Event>,
-    io_devices: (Option<IODeviceType>, Option<IODeviceType>),
+    event_list: BinaryHeap<Reverse<Event>>, // min-heap of events, by firing time
+    next_seq: u64,
 }
 
 impl Dispatcher {
@@ -33,123 +54,268 @@ impl Dispatcher {
             cpus_to_go: HashMap::new(),
             ios_to_go: HashMap::new(),
             current_process: None,
-            event_queue: VecDeque::new(),
-            io_devices: (None, None)
+            event_list: BinaryHeap::new(),
+            next_seq: 0,
         }
     }
+
+    /** Checks if a user process has the CPU */
+    pub fn is_executing(&self) -> bool {
+        self.current_process.is_some()
+    }
+
+    /** Checks if the CPU is free and a process is ready to run on it */
+    pub fn needs_dispatch(&self, os: &OS) -> bool {
+        self.current_process.is_none()
+            && match os.input_params.pro_algorithm {
+                Algorithm::MLFQ => !os.feedback_queue.is_empty(),
+                _ => !os.ready_queue.is_empty(),
+            }
+    }
+
+    /** DISPATCH: give the CPU to the next ready process */
     pub fn dispatch(&mut self, os: &mut OS) {
-        // is the dispatcher currently executing a process right now?
-        if let Some(pid) = self.current_process {
-            self.exec(os, pid);
-        } else {
-            Self::get_next_pid_FIFO(os).map(|next_pid| {
-                self.exec(os, next_pid);
-            });
+        if let Some(next_pid) = Self::get_next_pid(os) {
+            self.start_exec(os, next_pid);
+        }
+    }
+
+    /** Periodically move every process back to the top MLFQ level to stop starvation */
+    pub fn boost(&mut self, os: &mut OS) {
+        if os.input_params.pro_algorithm == Algorithm::MLFQ
+            && os.master_clock % os.feedback_queue.boost_interval == 0
+        {
+            os.feedback_queue.boost();
         }
-        // update IOs for all blocked processes
-        self.update_ios(os);
+    }
+
+    /** Adds an event to the event list, to fire `units` clock cycles after the next one.
+    Events too far off for an i32 clock are put at i32::MAX */
+    fn set_event(&mut self, _type: EventType, clock: i32, units: i32, pid: PID) {
+        let time = clock.saturating_add(1).saturating_add(units);
+        self.event_list.push(Reverse(Event {
+            _type,
+            time,
+            pid,
+            seq: self.next_seq,
+        }));
+        self.next_seq += 1;
+    }
+
+    /** Removes the pending event of the given type for a process */
+    fn remove_event(&mut self, _type: EventType, pid: PID) {
+        self.event_list
+            .retain(|Reverse(event)| !(event._type == _type && event.pid == pid));
+    }
+
+    /** The next timeout or IO completion event for a process, as "T:609" or "I:624" */
+    pub fn pending_event(&self, pid: PID) -> Option<String> {
+        self.event_list
+            .iter()
+            .map(|Reverse(event)| event)
+            .filter(|event| event.pid == pid)
+            .min()
+            .map(|event| match event._type {
+                EventType::Timeout => format!("T:{}", event.time),
+                EventType::IO => format!("I:{}", event.time),
+            })
+    }
 
-        // process event queue
-        self.process_events(os);
+    /** Fires every event in the event list that is due at or before the current clock time,
+    in time order, by raising its interrupt */
+    pub fn check_event(&mut self, os: &OS, ic: &mut InterruptController) {
+        while self
+            .event_list
+            .peek()
+            .is_some_and(|Reverse(event)| event.time <= os.master_clock)
+        {
+            let Reverse(event) = self.event_list.pop().unwrap();
+            match event._type {
+                EventType::IO => ic.raise(Interrupt::IoInt, event.pid),
+                EventType::Timeout => ic.raise(Interrupt::TiInt, event.pid),
+            }
+        }
     }
 
-    /** Execute a process */
-    fn exec(&mut self, os: &mut OS, pid: PID) {
+    /** Give the CPU to a ready process */
+    fn start_exec(&mut self, os: &mut OS, pid: PID) {
         if let Some(proc) = os.running_processes.get_mut(&pid) {
             let clock = os.master_clock;
-
             // start the process, if we haven't already started
             if proc.start_time == 0 {
-                self.event_queue.push_back(Event {
-                    _type: EventType::Started,
-                    time: clock,
-                    pid: clock,
-                });
+                println!(
+                    "Process {} (PID # {}) started at clock time {}",
+                    proc.info.process_name, pid, clock
+                );
+                proc.start_time = clock;
             }
-
-            // check if the dispatcher was previously executing a process, and use that CPU info
-            match self.cpus_to_go.get(&pid) {
-                Some(_) => {
-                    if !os.blocked_queue.contains(&pid) {
-                        self.update_cpu(proc, clock);
-                    }
-                }
-                None => {
-                    let info_vec = &mut proc.info.run_info;
-                    // need new run info, pop from runinfo vec.
-                    if let Some(info) = &info_vec.pop() {
-                        // update CPU cycles to go
-
-                        self.cpus_to_go.insert(pid, info.CPU_units);
-                        // set currently executing
-                        os.current_pid = pid;
-                        self.current_process = Some(pid);
-
-
-                        // update IO cycles to go
-                        if info.IO_units > 0 {
-                            self.ios_to_go
-                                .insert(pid, (info.IO_device_type, info.IO_units));
-                        }
-                        self.update_cpu(proc, clock);
-                    } else {
-                        // info is empty, process must have been completed!
-                        self.event_queue.push_back(Event {
-                            _type: EventType::Finished,
-                            time: os.master_clock,
-                            pid,
-                        });
-                    }
-                }
+            proc.state = ProcessState::Executing;
+            proc.context_switches += 1;
+            os.current_pid = pid;
+            self.current_process = Some(pid);
+            // the timer interrupt preempts the process once its time slice is used up.
+            // it starts executing on the next clock cycle
+            let quantum = match os.input_params.pro_algorithm {
+                Algorithm::MLFQ => os.feedback_queue.quantum_of(pid),
+                _ => os.input_params.init_quantum,
+            };
+            if quantum > 0 {
+                self.set_event(EventType::Timeout, clock, quantum, pid);
             }
         }
     }
 
-    /** update IO cycles completed */
-    fn update_ios(&mut self, os: &mut OS) {
-        for (pid, togo) in self.ios_to_go.iter_mut() {
-            let proc = os
-                .running_processes
-                .get_mut(pid)
-                .expect("Could not find process");
+    /** Executes one CPU unit of the running process. At the end of its CPU burst
+    the process makes a system call, for IO or to finish.
+    @returns
+    Some(PID) of the process that used the CPU
+    None if the CPU was idle */
+    pub fn user_process(&mut self, os: &mut OS, ic: &mut InterruptController) -> Option<PID> {
+        let pid = self.current_process?;
+        let proc = os.running_processes.get_mut(&pid)?;
 
-            if let Some(curr_proc) = self.current_process {
-                if !os.blocked_queue.contains(&proc.pid) && proc.pid == curr_proc {
-                    os.blocked_queue.push_back(proc.pid);
-                    proc.state = ProcessState::Blocked;
-                    println!("blocked queue: {:?} at time: {}", os.blocked_queue, os.master_clock);
+        // check if the dispatcher was previously executing a process, and use that CPU info
+        let togo = match self.cpus_to_go.entry(pid) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // need new run info, take the next cycle from the runinfo vec.
+                if proc.info.run_info.is_empty() {
+                    // info is empty, process must have been completed!
+                    ic.raise(Interrupt::ScInt, pid);
+                    return Some(pid);
                 }
+                let info = proc.info.run_info.remove(0);
+                // IO is requested once this CPU burst is done
+                if info.IO_units > 0 {
+                    self.ios_to_go
+                        .insert(pid, (info.IO_device_type, info.IO_units));
+                }
+                // update CPU cycles to go
+                entry.insert(info.CPU_units)
             }
+        };
 
-            if togo.1 > 0 {
-                proc.total_ios += 1;
-                togo.1 -= 1;
-            } else {
-                self.event_queue.push_back(Event {
-                    _type: EventType::IO,
-                    time: os.master_clock,
-                    pid: *pid,
-                });
-            }
-        }
-    }
-
-    /** Update CPU cycles completed */
-    fn update_cpu(&mut self, proc: &mut ProcessControlBlock, clock: i32) {
-        let togo = self.cpus_to_go.get_mut(&proc.pid).unwrap();
         // update total CPU time for the currently running process
         if *togo > 0 {
             // info block has more cycles to go
-            proc.state = ProcessState::Executing;
             proc.total_cpu += 1;
             *togo -= 1;
-        } else {
+        }
+        if *togo == 0 {
             // done executing CPU for this info block
-            self.event_queue.push_back(Event {
-                _type: EventType::Timeout,
-                time: clock,
-                pid: proc.pid,
-            })
+            self.cpus_to_go.remove(&pid);
+            if self.ios_to_go.contains_key(&pid) || proc.info.run_info.is_empty() {
+                ic.raise(Interrupt::ScInt, pid);
+            }
+        }
+        Some(pid)
+    }
+
+    /** SC_INT: the running process finished a CPU burst and requested IO, or is done */
+    pub fn sc_handler(&mut self, os: &mut OS, ic: &mut InterruptController, pid: PID) {
+        let proc = match os.running_processes.get_mut(&pid) {
+            Some(proc) => proc,
+            None => return,
+        };
+        // the burst finished before the quantum ran out
+        self.remove_event(EventType::Timeout, pid);
+        self.current_process = None;
+
+        if self.ios_to_go.contains_key(&pid) {
+            println!(
+                "Process {} (PID # {}) blocked for IO at clock time {}",
+                proc.info.process_name, pid, os.master_clock
+            );
+            proc.state = ProcessState::Blocked;
+            os.blocked_queue.push_back(pid);
+            // gave up the CPU before its quantum ran out
+            os.feedback_queue.promote(pid);
+            ic.raise(Interrupt::Ios, pid);
+        } else {
+            println!(
+                "Completed: {} at MC time: {}",
+                proc.info.process_name, os.master_clock
+            );
+            proc.state = ProcessState::Done;
+            proc.end_time = os.master_clock;
+            ic.raise(Interrupt::Dones, pid);
+        }
+    }
+
+    /** TI_INT: places the timed-out process at the end of the ready list.
+    The rest of its CPU burst stays in cpus_to_go for the next dispatch */
+    pub fn timer_handler(&mut self, os: &mut OS, pid: PID) {
+        // the process may have made a system call on the same cycle its quantum ran out
+        if self.current_process != Some(pid) {
+            return;
+        }
+        if let Some(proc) = os.running_processes.get_mut(&pid) {
+            println!(
+                "Process {} (PID # {}) timed out at clock time {}",
+                proc.info.process_name, pid, os.master_clock
+            );
+            self.current_process = None;
+            proc.state = ProcessState::Ready;
+            // used its whole quantum
+            os.feedback_queue.demote(pid);
+            os.set_ready(pid);
+        }
+    }
+
+    /** IO_INT: a process's IO completed; it is ready to run again */
+    pub fn io_handler(&mut self, os: &mut OS, ic: &mut InterruptController, pid: PID) {
+        if let Some(proc) = os.running_processes.get_mut(&pid) {
+            println!(
+                "IO for process {} (PID {}) completed at clock time {}",
+                proc.info.process_name, pid, os.master_clock
+            );
+            if let Some((_, units)) = self.ios_to_go.remove(&pid) {
+                proc.total_ios = proc.total_ios.saturating_add(units);
+            }
+            os.blocked_queue.retain(|other| *other != pid);
+            proc.state = ProcessState::Ready;
+            os.set_ready(pid);
+            // hand the device to the next process waiting for one of its type
+            if let Some(next_pid) = os.devices.release(pid) {
+                ic.raise(Interrupt::Ios, next_pid);
+            }
+        }
+    }
+
+    /** IOS: gets a device for a blocked process and starts its IO,
+    or leaves it pending until a unit of the device type is free */
+    pub fn ios_routine(&mut self, os: &mut OS, pid: PID) {
+        let (dtype, units) = match self.ios_to_go.get(&pid) {
+            Some(request) => *request,
+            None => return,
+        };
+        let has_device = os.devices.device_of(pid).is_some();
+        if has_device || os.devices.request(dtype, pid).is_some() {
+            self.set_event(EventType::IO, os.master_clock, units, pid);
+        } else {
+            println!(
+                "PID # {} pending on {:?} device at clock time {}",
+                pid, dtype, os.master_clock
+            );
+        }
+    }
+
+    /** DONES: removes a completed process from the system */
+    pub fn dones_routine(&mut self, os: &mut OS, pid: PID) {
+        self.cpus_to_go.remove(&pid);
+        self.ios_to_go.remove(&pid);
+        self.event_list.retain(|Reverse(other)| other.pid != pid);
+        if let Some(summary) = os.remove_process(pid) {
+            println!("{}", summary);
+        }
+    }
+
+    /** get next PID required to execute, using the scheduling algorithm from the OS params */
+    fn get_next_pid(os: &mut OS) -> Option<PID> {
+        match os.input_params.pro_algorithm {
+            Algorithm::IPRI => Self::get_next_pid_IPRI(os),
+            Algorithm::MLFQ => Self::get_next_pid_MLFQ(os),
+            _ => Self::get_next_pid_FIFO(os),
         }
     }
 
@@ -158,53 +324,60 @@ impl Dispatcher {
     Some(PID) for the next PID in the ready queue
     None if nothing is in the ready queue*/
     fn get_next_pid_FIFO(os: &mut OS) -> Option<PID> {
-        let fifo = &mut os.ready_queue;
-        fifo.pop_front().and_then(|T| {
-            fifo.push_back(T);
-            Some(T)
-        })
-    }
-
-    fn process_events(&mut self, os: &mut OS) {
-        // process all events in the queue with drain(..)
-        for event in self.event_queue.drain(..) {
-            if let Some(proc) = os.running_processes.get_mut(&event.pid) {
-                match event._type {
-                    EventType::IO => {
-                        println!(
-                            "IO for process {} (PID {}) completed at clock time {}",
-                            proc.info.process_name, event.pid, event.time
-                        );
-                        self.ios_to_go.remove(&event.pid);
-                        os.blocked_queue.pop_front();
-                    }
-                    EventType::Timeout => {
-                        println!(
-                            "Process {} (PID # {}) timed out at clock time {}",
-                            proc.info.process_name, event.pid, event.time
-                        );
-                        self.current_process = None;
-                        self.cpus_to_go.remove(&event.pid);
-                        proc.state = ProcessState::Ready;
-                    }
-                    EventType::Finished => {
-                        println!(
-                            "Process {} (PID # {}) completed at clock time {}",
-                            proc.info.process_name, event.pid, event.time
-                        );
-                        proc.state = ProcessState::Done;
-                        self.current_process = None;
-                        os.remove_process(event.pid);
-                    }
-                    EventType::Started => {
-                        println!(
-                            "Process {} (PID # {}) started at clock time {}",
-                            proc.info.process_name, event.pid, event.time
-                        );
-                        proc.start_time = event.time;
-                    }
-                }
-            }
-        }
+        os.ready_queue.pop_front()
+    }
+
+    /** get the highest priority PID in the ready queue. The ready queue is kept in priority order,
+    so this is the front.
+    @returns
+    Some(PID) for the next PID in the ready queue
+    None if nothing is in the ready queue*/
+    fn get_next_pid_IPRI(os: &mut OS) -> Option<PID> {
+        os.ready_queue.pop_front()
+    }
+
+    /** get the next PID from the highest non-empty level of the feedback queue
+    @returns
+    Some(PID) for the next PID in the feedback queue
+    None if every level is empty*/
+    fn get_next_pid_MLFQ(os: &mut OS) -> Option<PID> {
+        os.feedback_queue.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os::interrupts::RoutineCosts;
+    use crate::os::memory::MemoryOptions;
+    use crate::os::mlfq::FeedbackOptions;
+    use crate::records::{OSParams, ProcessData, RunInfo};
+
+    #[test]
+    fn huge_quanta_and_io_bursts_dont_overflow_the_clock() {
+        let params = OSParams {
+            init_quantum: i32::MAX,
+            ..OSParams::with_fixed_blocks(1000, 10)
+        };
+        let process = ProcessData {
+            run_info: vec![RunInfo {
+                CPU_units: 5,
+                IO_units: i32::MAX,
+                IO_device_type: IODeviceType::Disk,
+            }],
+            ..ProcessData::with_memsize(10)
+        };
+        let mut os = OS::new(
+            params,
+            vec![process],
+            50,
+            MemoryOptions::default(),
+            FeedbackOptions::default(),
+            RoutineCosts::default(),
+        )
+        .unwrap();
+        // the process blocks for IO that never finishes before the clock limit
+        assert!(os.start().is_empty());
+        assert_eq!(os.master_clock, 51);
     }
 }
@@ -0,0 +1,34 @@
+use crate::os::mlfq::MAX_LEVELS;
+use crate::records::MemModel;
+use std::error::Error;
+use std::fmt;
+
+/** OS parameters or options that can't be simulated */
+#[derive(Debug)]
+pub enum ConfigError {
+    UnknownMemModel(MemModel),
+    InvalidFeedbackLevels(usize), // MLFQ needs 1 to MAX_LEVELS levels
+    InvalidBoostInterval(i32),    // MLFQ boosts every n clock units, so n must be positive
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::UnknownMemModel(model) => {
+                write!(f, "Unsupported memory model {:?} in O/S parameters", model)
+            }
+            ConfigError::InvalidFeedbackLevels(levels) => write!(
+                f,
+                "MLFQ needs from 1 to {} levels, not {}",
+                MAX_LEVELS, levels
+            ),
+            ConfigError::InvalidBoostInterval(interval) => write!(
+                f,
+                "MLFQ boost interval must be a positive number of cycles, not {}",
+                interval
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
@@ -0,0 +1,313 @@
+use crate::os::memory::AllocResult::{self, *};
+use crate::os::memory::{MemoryManager, MemoryRange};
+use crate::os::process::PID;
+use crate::records::ProcessData;
+use std::fmt;
+
+/** State of a fixed memory block: MEM_FREE or MEM_USED */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BlockState {
+    Free,
+    Used,
+}
+
+/** How the blocks for a new process are chosen */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Placement {
+    Contiguous, // the lowest numbered run of free blocks that is long enough
+    Scattered,  // the lowest numbered free blocks, wherever they are
+}
+
+/** One entry of the fixed block memory list */
+#[derive(Debug, Copy, Clone)]
+pub struct Block {
+    pub state: BlockState,
+    pub pid: PID, // owning process, or 0 when the block is free
+}
+
+/** Fixed-size block memory. Blocks are numbered from 1 */
+pub struct FixedBlocks {
+    pub placement: Placement,
+    pub block_size: i32,   // bytes per block
+    pub total_blocks: i32, // number of available blocks
+    blocks: Vec<Block>,    // fixed_block_memory_list; block n is at index n - 1
+}
+
+impl FixedBlocks {
+    pub fn new(block_size: i32, total_blocks: i32, placement: Placement) -> Self {
+        let free = Block {
+            state: BlockState::Free,
+            pid: 0,
+        };
+        Self {
+            placement,
+            block_size,
+            total_blocks,
+            blocks: vec![free; total_blocks.max(0) as usize],
+        }
+    }
+
+    /** The block map entry for a block number, starting at 1 */
+    pub fn block(&self, number: i32) -> Option<&Block> {
+        self.blocks.get((number - 1) as usize)
+    }
+
+    /** Number of blocks a process needs. Process memory sizes are in KB */
+    fn blocks_needed(&self, info: &ProcessData) -> i32 {
+        info.process_memsize / (self.block_size / 1000)
+    }
+
+    /** Finds the lowest numbered run of `count` free blocks
+    @returns
+    Some(first block) of the run
+    None if there is no run that long */
+    fn find_free_run(&self, count: i32) -> Option<i32> {
+        if count <= 0 {
+            return Some(1);
+        }
+        let mut run = 0;
+        for (idx, block) in self.blocks.iter().enumerate() {
+            run = match block.state {
+                BlockState::Free => run + 1,
+                BlockState::Used => 0,
+            };
+            if run == count {
+                return Some(idx as i32 + 2 - count);
+            }
+        }
+        None
+    }
+
+    /** Finds the lowest numbered `count` free blocks, contiguous or not
+    @returns
+    Some(first and last block of each run) of the blocks found
+    None if fewer than `count` blocks are free */
+    fn find_free_blocks(&self, count: i32) -> Option<Vec<(i32, i32)>> {
+        let mut extents: Vec<(i32, i32)> = Vec::new();
+        let mut found = 0;
+        for (idx, block) in self.blocks.iter().enumerate() {
+            if found == count {
+                break;
+            }
+            if block.state == BlockState::Used {
+                continue;
+            }
+            let number = idx as i32 + 1;
+            match extents.last_mut() {
+                Some((_, last)) if *last + 1 == number => *last = number,
+                _ => extents.push((number, number)),
+            }
+            found += 1;
+        }
+        if found < count {
+            return None;
+        }
+        Some(extents)
+    }
+}
+
+impl MemoryManager for FixedBlocks {
+    /** Finds free fixed-size blocks for a process using the placement policy,
+    and marks them used by it */
+    fn allocate(&mut self, pid: PID, info: &ProcessData) -> AllocResult {
+        let needed = self.blocks_needed(info);
+
+        // check if this process will even fit in our total memory
+        if needed > self.total_blocks {
+            return TooBig;
+        }
+        let found = match self.placement {
+            Placement::Contiguous => self
+                .find_free_run(needed)
+                .map(|first| vec![(first, first + needed - 1)]),
+            Placement::Scattered => self.find_free_blocks(needed),
+        };
+        match found {
+            Some(extents) => {
+                for (first, last) in &extents {
+                    for block in &mut self.blocks[(first - 1) as usize..*last as usize] {
+                        block.state = BlockState::Used;
+                        block.pid = pid;
+                    }
+                }
+                Allocated(MemoryRange::Blocks(extents))
+            }
+            // didn't find any room
+            None => NoSpace,
+        }
+    }
+
+    /** Marks every block the process owns as free */
+    fn free(&mut self, pid: PID, _range: &MemoryRange) {
+        for block in self.blocks.iter_mut().filter(|block| block.pid == pid) {
+            block.state = BlockState::Free;
+            block.pid = 0;
+        }
+    }
+}
+
+impl fmt::Display for FixedBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let free: Vec<String> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.state == BlockState::Free)
+            .map(|(idx, _)| (idx + 1).to_string())
+            .collect();
+        writeln!(f, "FreeBlks: {}", free.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::quickcheck;
+    use std::collections::{HashMap, HashSet};
+
+    const BLOCK_SIZE: i32 = 5000;
+    const TOTAL_BLOCKS: i32 = 20;
+
+    fn blocks_of(range: &MemoryRange) -> Vec<i32> {
+        match range {
+            MemoryRange::Blocks(extents) => extents
+                .iter()
+                .flat_map(|(first, last)| *first..=*last)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /** Runs a sequence of operations: (true, n) allocates a new process needing n % 8 blocks,
+    (false, n) frees the n-th process still in memory. Checks after every step that the
+    block map matches the ranges handed out, and that no two processes share a block */
+    fn check_ops(placement: Placement, ops: Vec<(bool, u8)>) -> bool {
+        let mut memory = FixedBlocks::new(BLOCK_SIZE, TOTAL_BLOCKS, placement);
+        let mut ranges: HashMap<PID, MemoryRange> = HashMap::new();
+        let mut next_pid = 1;
+        for (alloc, n) in ops {
+            if alloc {
+                let blocks = i32::from(n % 8);
+                let pid = next_pid;
+                next_pid += 1;
+                match memory.allocate(pid, &ProcessData::with_memsize(blocks * BLOCK_SIZE / 1000)) {
+                    Allocated(range) => {
+                        ranges.insert(pid, range);
+                    }
+                    NoSpace => {
+                        // there really weren't enough free blocks for the placement policy
+                        let used: HashSet<i32> = ranges.values().flat_map(blocks_of).collect();
+                        let fits = match placement {
+                            Placement::Contiguous => (1..=TOTAL_BLOCKS - blocks + 1)
+                                .any(|first| (first..first + blocks).all(|b| !used.contains(&b))),
+                            Placement::Scattered => TOTAL_BLOCKS - used.len() as i32 >= blocks,
+                        };
+                        if fits {
+                            return false;
+                        }
+                    }
+                    TooBig => return false,
+                }
+            } else if !ranges.is_empty() {
+                let mut pids: Vec<PID> = ranges.keys().cloned().collect();
+                pids.sort();
+                let pid = pids[n as usize % pids.len()];
+                let range = ranges.remove(&pid).unwrap();
+                memory.free(pid, &range);
+            }
+
+            // every block is owned by the process whose range it's in, and by no other
+            let mut owner: HashMap<i32, PID> = HashMap::new();
+            for (pid, range) in &ranges {
+                for number in blocks_of(range) {
+                    if !(1..=TOTAL_BLOCKS).contains(&number) || owner.insert(number, *pid).is_some()
+                    {
+                        return false;
+                    }
+                }
+            }
+            for number in 1..=TOTAL_BLOCKS {
+                let block = memory.block(number).unwrap();
+                let expected = owner.get(&number);
+                let ok = match block.state {
+                    BlockState::Used => expected == Some(&block.pid),
+                    BlockState::Free => expected.is_none() && block.pid == 0,
+                };
+                if !ok {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn no_two_pids_own_the_same_block() {
+        fn contiguous(ops: Vec<(bool, u8)>) -> bool {
+            check_ops(Placement::Contiguous, ops)
+        }
+        fn scattered(ops: Vec<(bool, u8)>) -> bool {
+            check_ops(Placement::Scattered, ops)
+        }
+        quickcheck(contiguous as fn(Vec<(bool, u8)>) -> bool);
+        quickcheck(scattered as fn(Vec<(bool, u8)>) -> bool);
+    }
+
+    #[test]
+    fn first_allocation_starts_at_block_1() {
+        let mut memory = FixedBlocks::new(BLOCK_SIZE, TOTAL_BLOCKS, Placement::Contiguous);
+        match memory.allocate(1, &ProcessData::with_memsize(40)) {
+            Allocated(MemoryRange::Blocks(extents)) => assert_eq!(extents, vec![(1, 8)]),
+            _ => panic!("expected blocks 1 to 8"),
+        }
+    }
+
+    #[test]
+    fn freed_gap_is_reused_without_overlap() {
+        let mut memory = FixedBlocks::new(BLOCK_SIZE, TOTAL_BLOCKS, Placement::Contiguous);
+        let sizes = [(1, 20), (2, 15), (3, 25)]; // 4, 3 and 5 blocks
+        let mut ranges = HashMap::new();
+        for (pid, memsize) in sizes.iter() {
+            if let Allocated(range) = memory.allocate(*pid, &ProcessData::with_memsize(*memsize)) {
+                ranges.insert(*pid, range);
+            }
+        }
+        memory.free(2, &ranges[&2]);
+        // blocks 5 to 7 are free again, between pid 1 and pid 3
+        match memory.allocate(4, &ProcessData::with_memsize(15)) {
+            Allocated(MemoryRange::Blocks(extents)) => assert_eq!(extents, vec![(5, 7)]),
+            _ => panic!("expected blocks 5 to 7"),
+        }
+        // a 4 block process doesn't fit in the gap, so goes after pid 3
+        match memory.allocate(5, &ProcessData::with_memsize(20)) {
+            Allocated(MemoryRange::Blocks(extents)) => assert_eq!(extents, vec![(13, 16)]),
+            _ => panic!("expected blocks 13 to 16"),
+        }
+    }
+
+    #[test]
+    fn scattered_blocks_fill_freed_gaps() {
+        let mut memory = FixedBlocks::new(BLOCK_SIZE, TOTAL_BLOCKS, Placement::Scattered);
+        let sizes = [(1, 20), (2, 15), (3, 25), (4, 30)]; // 4, 3, 5 and 6 blocks
+        let mut ranges = HashMap::new();
+        for (pid, memsize) in sizes.iter() {
+            if let Allocated(range) = memory.allocate(*pid, &ProcessData::with_memsize(*memsize)) {
+                ranges.insert(*pid, range);
+            }
+        }
+        memory.free(2, &ranges[&2]);
+        // blocks 5 to 7 and 19 to 20 are free; no run of 4, but 4 blocks altogether
+        match memory.allocate(5, &ProcessData::with_memsize(20)) {
+            Allocated(range) => {
+                assert_eq!(blocks_of(&range), vec![5, 6, 7, 19]);
+                assert_eq!(range.to_string(), "5 6 7 19 ");
+            }
+            _ => panic!("expected blocks 5 6 7 19"),
+        }
+        // only block 20 is left
+        assert!(matches!(
+            memory.allocate(6, &ProcessData::with_memsize(10)),
+            NoSpace
+        ));
+    }
+}
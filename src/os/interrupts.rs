@@ -0,0 +1,240 @@
+use crate::os::allocator::Allocator;
+use crate::os::dispatcher::Dispatcher;
+use crate::os::os::OS;
+use crate::os::process::PID;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+/** Interrupt priority levels, highest priority first.
+The first three are serviced as soon as they are raised; the rest are OS routines,
+only one of which runs each clock cycle. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Interrupt {
+    IoInt,    // IO completion interrupt
+    ScInt,    // system call: a process finished its CPU burst
+    TiInt,    // timer interrupt: a process used up its quantum
+    Dispatch, // give the CPU to the next ready process
+    Ios,      // IO service routine: get a device and start a process's IO
+    Dones,    // tear down completed processes
+    Master,   // master scheduler: allocate processes from the input queue
+    Compact,  // move resident processes together to merge free memory
+}
+
+impl Interrupt {
+    /** Interrupts are handled straight away; OS routines take up a clock cycle */
+    fn is_handler(self) -> bool {
+        self < Interrupt::Dispatch
+    }
+}
+
+impl fmt::Display for Interrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Interrupt::IoInt => "IO_INT",
+            Interrupt::ScInt => "SC_INT",
+            Interrupt::TiInt => "TI_INT",
+            Interrupt::Dispatch => "DISPATCH",
+            Interrupt::Ios => "IOS",
+            Interrupt::Dones => "DONES",
+            Interrupt::Master => "MASTER",
+            Interrupt::Compact => "COMPACT",
+        };
+        f.pad(name)
+    }
+}
+
+/** Clock cycles each OS routine takes, during which user processes don't run */
+#[derive(Debug, Copy, Clone)]
+pub struct RoutineCosts {
+    pub dispatch: i32,
+    pub ios: i32,            // CPU_IOS
+    pub dones: i32,          // CPU_DONES
+    pub master: i32,         // CPU_MASTER
+    pub compact_per_kb: i32, // CPU_COMPACT, for each KB of memory moved
+}
+
+impl Default for RoutineCosts {
+    fn default() -> Self {
+        Self {
+            dispatch: 1,
+            ios: 3,
+            dones: 3,
+            master: 4,
+            compact_per_kb: 1,
+        }
+    }
+}
+
+impl RoutineCosts {
+    /** Clock cycles an OS routine takes. Compaction takes longer the more memory it moves */
+    fn of(&self, level: Interrupt, os: &OS) -> i32 {
+        match level {
+            Interrupt::Dispatch => self.dispatch,
+            Interrupt::Ios => self.ios,
+            Interrupt::Dones => self.dones,
+            Interrupt::Master => self.master,
+            Interrupt::Compact => {
+                (os.memory.compaction_bytes() / 1000).saturating_mul(self.compact_per_kb)
+            }
+            _ => 0,
+        }
+    }
+}
+
+/** What the CPU did in a clock cycle */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CpuActivity {
+    Routine(Interrupt), // ran an OS routine
+    User(PID),          // ran a user process
+    Idle,               // nothing to do; waiting on IO
+}
+
+/** Queues raised interrupts by priority level and services them in priority order */
+pub struct InterruptController {
+    pending: BTreeMap<Interrupt, VecDeque<PID>>,
+    os_counter: i32, // cycles left for the OS routine in progress
+    in_progress: Option<(Interrupt, PID)>, // OS routine in progress, done when os_counter hits 0
+}
+
+impl Default for InterruptController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            os_counter: 0,
+            in_progress: None,
+        }
+    }
+
+    /** Raises an interrupt on behalf of a process */
+    pub fn raise(&mut self, level: Interrupt, pid: PID) {
+        self.pending.entry(level).or_default().push_back(pid);
+    }
+
+    /** Raises an interrupt unless one is already pending at that level */
+    pub fn raise_once(&mut self, level: Interrupt) {
+        if !self.is_pending(level) {
+            self.raise(level, 0);
+        }
+    }
+
+    pub fn is_pending(&self, level: Interrupt) -> bool {
+        self.pending
+            .get(&level)
+            .is_some_and(|queue| !queue.is_empty())
+    }
+
+    /** Takes the oldest request at the highest pending priority level */
+    fn take(&mut self) -> Option<(Interrupt, PID)> {
+        let (&level, queue) = self
+            .pending
+            .iter_mut()
+            .find(|(_, queue)| !queue.is_empty())?;
+        queue.pop_front().map(|pid| (level, pid))
+    }
+
+    /** The OS routine that will run on the next clock cycle, if any */
+    pub fn next_routine(&self, os: &OS, dispatcher: &Dispatcher) -> Option<Interrupt> {
+        if let Some((level, _)) = self.in_progress {
+            return Some(level);
+        }
+        if dispatcher.needs_dispatch(os) {
+            return Some(Interrupt::Dispatch);
+        }
+        self.pending
+            .iter()
+            .find(|(level, queue)| !level.is_handler() && !queue.is_empty())
+            .map(|(level, _)| *level)
+    }
+
+    /** Runs one clock cycle:
+    1) fires due timeout and IO events, then services every interrupt handler in priority order
+    2) runs the highest priority OS routine, deferring the rest to later cycles
+    3) otherwise gives the cycle to the running user process */
+    pub fn cycle(&mut self, os: &mut OS, dispatcher: &mut Dispatcher) -> CpuActivity {
+        dispatcher.check_event(os, self);
+
+        // interrupt handlers: IO_INT, SC_INT and TI_INT, in that order
+        while self
+            .pending
+            .iter()
+            .any(|(level, queue)| level.is_handler() && !queue.is_empty())
+        {
+            match self.take() {
+                Some((Interrupt::IoInt, pid)) => dispatcher.io_handler(os, self, pid),
+                Some((Interrupt::ScInt, pid)) => dispatcher.sc_handler(os, self, pid),
+                Some((Interrupt::TiInt, pid)) => dispatcher.timer_handler(os, pid),
+                _ => unreachable!("only interrupt handlers are pending"),
+            }
+        }
+
+        // OS routines: DISPATCH, IOS, DONES, MASTER and COMPACT; one at a time, each taking
+        // its cost in clock cycles. The routine's work is done on its last cycle
+        if self.in_progress.is_none() {
+            let routine = match self.next_routine(os, dispatcher) {
+                Some(Interrupt::Dispatch) => Some((Interrupt::Dispatch, 0)),
+                Some(_) => self.take(),
+                None => None,
+            };
+            if let Some((level, pid)) = routine {
+                self.in_progress = Some((level, pid));
+                self.os_counter = os.routine_costs.of(level, os).max(1);
+            }
+        }
+        let activity = match self.in_progress {
+            Some((level, pid)) => {
+                os.os_overhead += 1;
+                self.os_counter -= 1;
+                if self.os_counter == 0 {
+                    self.in_progress = None;
+                    self.run_routine(os, dispatcher, level, pid);
+                }
+                CpuActivity::Routine(level)
+            }
+            // no OS work pending: the user process gets the CPU
+            None => match dispatcher.user_process(os, self) {
+                Some(pid) => CpuActivity::User(pid),
+                None => CpuActivity::Idle,
+            },
+        };
+
+        dispatcher.boost(os);
+        activity
+    }
+
+    /** Does the work of an OS routine */
+    fn run_routine(
+        &mut self,
+        os: &mut OS,
+        dispatcher: &mut Dispatcher,
+        level: Interrupt,
+        pid: PID,
+    ) {
+        match level {
+            Interrupt::Dispatch => dispatcher.dispatch(os),
+            Interrupt::Ios => dispatcher.ios_routine(os, pid),
+            Interrupt::Dones => {
+                dispatcher.dones_routine(os, pid);
+                // memory was freed, so input queue processes may fit now
+                self.raise_once(Interrupt::Master);
+            }
+            Interrupt::Master => {
+                // a process that only fits once memory is compacted
+                if Allocator::allocate(os) {
+                    self.raise_once(Interrupt::Compact);
+                }
+            }
+            Interrupt::Compact => {
+                Allocator::compact(os);
+                // there's one big hole now, so the input queue gets another try
+                self.raise_once(Interrupt::Master);
+            }
+            _ => unreachable!("{} is not an OS routine", level),
+        }
+    }
+}
@@ -1,14 +1,174 @@
+use crate::os::buddy::BuddySystem;
+use crate::os::error::ConfigError;
+use crate::os::fixed::{FixedBlocks, Placement};
+use crate::os::paging::{PageTable, PagedMemory};
+use crate::os::partition::{FitPolicy, VariablePartitions};
+use crate::os::process::PID;
+use crate::records::{MemModel, OSParams, ProcessData};
+use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Clone)]
-pub struct MemoryRange(pub i32, pub i32); // initial and final blocks of memory this process takes up
+pub enum MemoryRange {
+    Blocks(Vec<(i32, i32)>), // (initial, final) block of each run of blocks this process takes up
+    Bytes(i32, i32),         // first and last byte of a variable partition
+    Pages(PageTable),        // the frame each page of the process is in
+    Unlimited,               // no memory management, the process isn't given a location
+}
 
 impl fmt::Display for MemoryRange {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut result = String::new();
-        for i in self.0..=self.1 {
-            result.push_str(&(i.to_string() + " "));
+        match self {
+            MemoryRange::Blocks(extents) => {
+                let mut result = String::new();
+                for (first, last) in extents {
+                    for i in *first..=*last {
+                        result.push_str(&(i.to_string() + " "));
+                    }
+                }
+                write!(f, "{}", result)
+            }
+            MemoryRange::Bytes(first, last) => write!(f, "{}-{} ", first, last),
+            MemoryRange::Pages(table) => {
+                let mut result = String::new();
+                for frame in &table.frames {
+                    result.push_str(&(frame.to_string() + " "));
+                }
+                write!(f, "{}", result)
+            }
+            MemoryRange::Unlimited => write!(f, "- "),
+        }
+    }
+}
+
+// result of allocation attempt
+pub enum AllocResult {
+    NoSpace,
+    TooBig,
+    Allocated(MemoryRange),
+}
+
+/** A memory model the allocator can place processes with.
+Display prints the model's free memory for the periodic report */
+pub trait MemoryManager: fmt::Display {
+    /** Checks if memory is available for a given process, and reserves it if it is */
+    fn allocate(&mut self, pid: PID, info: &ProcessData) -> AllocResult;
+    /** Gives back the memory a process was allocated */
+    fn free(&mut self, pid: PID, range: &MemoryRange);
+
+    /** Checks if compacting memory would make room for a process that doesn't fit now */
+    fn compaction_helps(&self, _info: &ProcessData) -> bool {
+        false
+    }
+    /** Bytes of resident processes that compacting memory would move */
+    fn compaction_bytes(&self) -> i32 {
+        0
+    }
+    /** Moves resident processes together to merge their free memory,
+    updating the memory map with where each process is now
+    @returns
+    the number of bytes moved */
+    fn compact(&mut self, _memory_map: &mut HashMap<PID, MemoryRange>) -> i32 {
+        0
+    }
+}
+
+/** How memory is given out for the variable memory model */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VariableAllocator {
+    Partitions(FitPolicy), // exact sized partitions, placed in holes by the fit policy
+    Buddy,                 // power of two blocks from the binary buddy system
+}
+
+/** How memory is managed, beyond the memory model in the OS params */
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryOptions {
+    pub variable: VariableAllocator, // how variable memory is given out
+    pub placement: Placement,        // how fixed memory blocks are chosen for a process
+    pub compact_at: Option<f64>, // compact variable memory at this % external fragmentation; None never does
+}
+
+impl Default for MemoryOptions {
+    fn default() -> Self {
+        Self {
+            variable: VariableAllocator::Partitions(FitPolicy::First),
+            placement: Placement::Contiguous,
+            compact_at: None,
         }
-        write!(f, "{}", result)
+    }
+}
+
+/** Builds the memory manager for the memory model in the OS params */
+pub fn new_manager(
+    params: &OSParams,
+    options: &MemoryOptions,
+) -> Result<Box<dyn MemoryManager>, ConfigError> {
+    match params.mem_model {
+        MemModel::None => Ok(Box::new(Unlimited)),
+        MemModel::Fixed => Ok(Box::new(FixedBlocks::new(
+            params.mem_fix_block_size,
+            params.mem_fix_total_blocks,
+            options.placement,
+        ))),
+        MemModel::Variable => match options.variable {
+            VariableAllocator::Partitions(policy) => Ok(Box::new(VariablePartitions::new(
+                params.mem_var_totsize,
+                params.mem_var_maxsize,
+                policy,
+                options.compact_at,
+            ))),
+            VariableAllocator::Buddy => Ok(Box::new(BuddySystem::new(
+                params.mem_var_totsize,
+                params.mem_var_maxsize,
+            ))),
+        },
+        MemModel::Paged => Ok(Box::new(PagedMemory::new(
+            params.mem_fix_block_size,
+            params.mem_fix_total_blocks,
+        ))),
+        MemModel::Unknown => Err(ConfigError::UnknownMemModel(params.mem_model.clone())),
+    }
+}
+
+/** MemModel::None: every process fits, and memory is never used up */
+pub struct Unlimited;
+
+impl MemoryManager for Unlimited {
+    fn allocate(&mut self, _pid: PID, _info: &ProcessData) -> AllocResult {
+        AllocResult::Allocated(MemoryRange::Unlimited)
+    }
+    fn free(&mut self, _pid: PID, _range: &MemoryRange) {}
+}
+
+impl fmt::Display for Unlimited {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Checks a backend turns down processes whose size in bytes overflows an i32, or
+    comes close to it, without overflowing itself. Process memory sizes are in KB */
+    fn assert_huge_sizes_are_too_big(mut memory: impl MemoryManager) {
+        for memsize in [i32::MAX / 1000 + 1, i32::MAX, i32::MAX / 1000] {
+            let process = ProcessData::with_memsize(memsize);
+            assert!(matches!(memory.allocate(1, &process), AllocResult::TooBig));
+            assert!(!memory.compaction_helps(&process));
+        }
+    }
+
+    #[test]
+    fn sizes_too_big_for_an_i32_are_too_big_not_a_panic() {
+        assert_huge_sizes_are_too_big(VariablePartitions::new(
+            100000,
+            0,
+            FitPolicy::First,
+            Some(0.0),
+        ));
+        assert_huge_sizes_are_too_big(BuddySystem::new(100000, 0));
+        assert_huge_sizes_are_too_big(PagedMemory::new(4000, 10));
     }
 }
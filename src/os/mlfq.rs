@@ -0,0 +1,139 @@
+use crate::os::process::PID;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+pub const DEFAULT_LEVELS: usize = 3;
+pub const DEFAULT_BOOST_INTERVAL: i32 = 250;
+pub const MAX_LEVELS: usize = 16; // each level doubles the quantum, so the lowest is 2^15 times the first
+
+/** How the multi-level feedback queue is set up, beyond the initial quantum in the OS params */
+#[derive(Debug, Copy, Clone)]
+pub struct FeedbackOptions {
+    pub levels: usize,       // number of queue levels, from 1 to MAX_LEVELS
+    pub boost_interval: i32, // every n clock units, all processes move back to level 0
+}
+
+impl Default for FeedbackOptions {
+    fn default() -> Self {
+        Self {
+            levels: DEFAULT_LEVELS,
+            boost_interval: DEFAULT_BOOST_INTERVAL,
+        }
+    }
+}
+
+/** Multi-level feedback queue.
+Level 0 has the highest priority and the shortest quantum; each level below it
+doubles the quantum of the level above, up to i32::MAX. */
+pub struct FeedbackQueue {
+    levels: Vec<VecDeque<PID>>,
+    quanta: Vec<i32>,
+    level_of: HashMap<PID, usize>, // current level of every process known to the queue
+    pub boost_interval: i32,       // every n clock units, all processes move back to level 0
+}
+
+impl FeedbackQueue {
+    pub fn new(num_levels: usize, init_quantum: i32, boost_interval: i32) -> Self {
+        let num_levels = num_levels.max(1);
+        Self {
+            levels: vec![VecDeque::new(); num_levels],
+            quanta: (0..num_levels)
+                .map(|lvl| init_quantum.saturating_mul(1 << lvl.min(MAX_LEVELS - 1)))
+                .collect(),
+            level_of: HashMap::new(),
+            boost_interval,
+        }
+    }
+
+    /** Adds a ready process to the back of its current level. New processes start at level 0 */
+    pub fn push(&mut self, pid: PID) {
+        let level = *self.level_of.entry(pid).or_insert(0);
+        self.levels[level].push_back(pid);
+    }
+
+    /** Removes the next process from the highest priority non-empty level */
+    pub fn pop(&mut self) -> Option<PID> {
+        self.levels.iter_mut().find_map(|level| level.pop_front())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.iter().all(|level| level.is_empty())
+    }
+
+    /** Position of a process in the queue, counting from the front of level 0 */
+    pub fn position(&self, pid: PID) -> Option<usize> {
+        self.levels.iter().flatten().position(|other| *other == pid)
+    }
+
+    /** The time slice a process gets at its current level */
+    pub fn quantum_of(&self, pid: PID) -> i32 {
+        self.quanta[self.level(pid)]
+    }
+
+    pub fn level(&self, pid: PID) -> usize {
+        *self.level_of.get(&pid).unwrap_or(&0)
+    }
+
+    /** Process used up its whole quantum: move it down a level */
+    pub fn demote(&mut self, pid: PID) {
+        let lowest = self.levels.len() - 1;
+        let level = self.level_of.entry(pid).or_insert(0);
+        *level = (*level + 1).min(lowest);
+    }
+
+    /** Process blocked for IO before its quantum ran out: move it up a level */
+    pub fn promote(&mut self, pid: PID) {
+        let level = self.level_of.entry(pid).or_insert(0);
+        *level = level.saturating_sub(1);
+    }
+
+    /** Moves every process back to level 0 so nothing starves on the lower levels */
+    pub fn boost(&mut self) {
+        let (top, rest) = self.levels.split_at_mut(1);
+        for level in rest.iter_mut() {
+            top[0].extend(level.drain(..));
+        }
+        for level in self.level_of.values_mut() {
+            *level = 0;
+        }
+    }
+
+    /** Removes a process from the queue entirely */
+    pub fn remove(&mut self, pid: PID) {
+        for level in self.levels.iter_mut() {
+            level.retain(|other| *other != pid);
+        }
+        self.level_of.remove(&pid);
+    }
+}
+
+impl fmt::Display for FeedbackQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, (level, quantum)) in self.levels.iter().zip(&self.quanta).enumerate() {
+            let pids: Vec<String> = level.iter().map(|pid| pid.to_string()).collect();
+            writeln!(
+                f,
+                "MLFQ level {} (quantum {}):\t{}",
+                idx,
+                quantum,
+                pids.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quanta_double_each_level_and_stop_at_i32_max() {
+        let queue = FeedbackQueue::new(4, 10, DEFAULT_BOOST_INTERVAL);
+        assert_eq!(queue.quanta, vec![10, 20, 40, 80]);
+
+        let queue = FeedbackQueue::new(MAX_LEVELS, 100000, DEFAULT_BOOST_INTERVAL);
+        assert_eq!(queue.quanta[14], 1638400000);
+        assert_eq!(queue.quanta[15], i32::MAX);
+    }
+}
@@ -1,5 +1,13 @@
 mod allocator;
+mod buddy;
+mod devices;
 mod dispatcher;
-mod memory;
+pub mod error;
+pub mod fixed;
+pub mod interrupts;
+pub mod memory;
+pub mod mlfq;
 pub mod os;
+mod paging;
+pub mod partition;
 mod process;
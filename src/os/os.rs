@@ -1,14 +1,17 @@
-use crate::os::allocator::Allocator;
+use crate::os::devices::DeviceTable;
 use crate::os::dispatcher::Dispatcher;
-use crate::os::memory::MemoryRange;
-use crate::os::process::{ProcessControlBlock, PID};
-use crate::records::{OSParams, ProcessData};
+use crate::os::error::ConfigError;
+use crate::os::interrupts::{Interrupt, InterruptController, RoutineCosts};
+use crate::os::memory::{self, MemoryManager, MemoryOptions, MemoryRange};
+use crate::os::mlfq::{self, FeedbackOptions, FeedbackQueue};
+use crate::os::process::{ProcessControlBlock, ProcessSummary, PID};
+use crate::records::{Algorithm, OSParams, ProcessData};
 
 use itertools::sorted;
 use std::collections::{HashMap, VecDeque};
 
 // version info
-const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
+pub const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
 
 pub struct OS {
     // input data
@@ -17,54 +20,130 @@ pub struct OS {
     pub input_queue: VecDeque<ProcessData>,
     pub input_size: i32,
     clock_limit: i32,
+    pub print_detail: bool, // print the detail table every N units
 
     // running info
     pub running_processes: HashMap<PID, ProcessControlBlock>,
     pub master_clock: i32,
     pub current_pid: PID,
+    pub routine_costs: RoutineCosts, // clock cycles taken by each OS routine
+    pub os_overhead: i32,            // total clock cycles spent in OS routines
+    pub summaries: Vec<ProcessSummary>, // accounting for every de-allocated process
     pub memory_map: HashMap<PID, MemoryRange>,
+    pub memory: Box<dyn MemoryManager>, // manager for the memory model in the OS params
+    pub devices: DeviceTable,
 
     // queues
     pub blocked_queue: VecDeque<PID>,
     pub ready_queue: VecDeque<PID>,
+    pub feedback_queue: FeedbackQueue, // ready queue levels when using MLFQ scheduling
 }
 
 impl OS {
-    pub fn new(params: OSParams, processes: Vec<ProcessData>, clock_limit: i32) -> Self {
+    pub fn new(
+        params: OSParams,
+        processes: Vec<ProcessData>,
+        clock_limit: i32,
+        memory_options: MemoryOptions,
+        feedback_options: FeedbackOptions,
+        routine_costs: RoutineCosts,
+    ) -> Result<Self, ConfigError> {
         let mem_cap = params.mem_fix_total_blocks as usize;
         let num_procs = processes.len();
-        Self {
+        if feedback_options.levels < 1 || feedback_options.levels > mlfq::MAX_LEVELS {
+            return Err(ConfigError::InvalidFeedbackLevels(feedback_options.levels));
+        }
+        // the boost runs when master_clock % boost_interval == 0
+        if feedback_options.boost_interval < 1 {
+            return Err(ConfigError::InvalidBoostInterval(
+                feedback_options.boost_interval,
+            ));
+        }
+        let feedback_queue = FeedbackQueue::new(
+            feedback_options.levels,
+            params.init_quantum,
+            feedback_options.boost_interval,
+        );
+        let memory = memory::new_manager(&params, &memory_options)?;
+        let devices = DeviceTable::new(&params);
+        Ok(Self {
             input_params: params,
             input_procs: processes,
             input_queue: VecDeque::with_capacity(num_procs),
             input_size: num_procs as i32,
             clock_limit,
+            print_detail: true,
 
             running_processes: HashMap::with_capacity(num_procs),
             master_clock: 0,
             current_pid: 0,
+            routine_costs,
+            os_overhead: 0,
+            summaries: Vec::with_capacity(num_procs),
             memory_map: HashMap::with_capacity(mem_cap),
+            memory,
+            devices,
 
             blocked_queue: VecDeque::with_capacity(num_procs),
             ready_queue: VecDeque::with_capacity(num_procs),
-        }
+            feedback_queue,
+        })
     }
 
-    /** Start the OS Simulation */
-    pub fn start(&mut self) {
-        println!(
-            "Started OS Simulation version {}.",
-            VERSION.unwrap_or("(unknown)")
-        );
+    /** Start the OS Simulation
+    @returns
+    the completion accounting for every process, in the order they were de-allocated */
+    pub fn start(&mut self) -> Vec<ProcessSummary> {
+        println!("{}", self.input_params);
         for proc in &self.input_procs {
             self.input_queue.push_back(proc.clone())
         }
+        if self.input_params.pro_algorithm == Algorithm::IPRI {
+            self.sort_input_queue();
+        }
 
         self.loop_clock();
+        self.summaries.clone()
+    }
+
+    /** Sorts the input queue in initial priority order (lowest value first).
+    The sort is stable, so processes of equal priority keep their input file order */
+    fn sort_input_queue(&mut self) {
+        self.input_queue
+            .make_contiguous()
+            .sort_by_key(|proc| proc.process_priority);
+    }
+
+    /** Places a process on the ready queue, in either FIFO or IPRI order,
+    or on its current level of the feedback queue for MLFQ */
+    pub fn set_ready(&mut self, pid: PID) {
+        match self.input_params.pro_algorithm {
+            Algorithm::MLFQ => self.feedback_queue.push(pid),
+            Algorithm::IPRI => {
+                let priority = self.priority_of(pid);
+                // insert behind every process with the same or better priority
+                let idx = self
+                    .ready_queue
+                    .iter()
+                    .position(|other| self.priority_of(*other) > priority)
+                    .unwrap_or(self.ready_queue.len());
+                self.ready_queue.insert(idx, pid);
+            }
+            _ => self.ready_queue.push_back(pid),
+        }
     }
 
-    /** Remove a process from the OS */
-    pub fn remove_process(&mut self, pid: PID) {
+    /** Initial priority of a running process. Lower values are scheduled first */
+    fn priority_of(&self, pid: PID) -> i32 {
+        self.running_processes
+            .get(&pid)
+            .map_or(i32::MAX, |proc| proc.info.process_priority)
+    }
+
+    /** Remove a process from the OS
+    @returns
+    the completion accounting for the process, if it was running */
+    pub fn remove_process(&mut self, pid: PID) -> Option<ProcessSummary> {
         // remove from blocked queue
         for (idx, item) in self.blocked_queue.iter_mut().enumerate() {
             if *item == pid {
@@ -79,16 +158,28 @@ impl OS {
                 break;
             }
         }
-        // remove from memory map
-        self.memory_map.remove(&pid);
+        // free any IO device, or pending request for one
+        self.devices.remove(pid);
+        // remove from feedback queue
+        self.feedback_queue.remove(pid);
+        // remove from memory map, and give the memory back to the memory manager
+        if let Some(range) = self.memory_map.remove(&pid) {
+            self.memory.free(pid, &range);
+        }
         // remove from running processes table
-        self.running_processes.remove(&pid);
+        let proc = self.running_processes.remove(&pid)?;
+        let summary = ProcessSummary::new(&proc, self.master_clock);
+        self.summaries.push(summary.clone());
+        Some(summary)
     }
 
     /** Starts the OS clock*/
     fn loop_clock(&mut self) {
         let every_n = self.input_params.every_n_units;
         let mut dispatcher = Dispatcher::new();
+        let mut interrupts = InterruptController::new();
+        // the master scheduler allocates the first processes from the input queue
+        interrupts.raise_once(Interrupt::Master);
         loop {
             // increment the master clock
             self.master_clock += 1;
@@ -101,41 +192,73 @@ impl OS {
                 );
                 break;
             }
-            // allocate processes, if we allocated, this uses up a clock cycle so we
-            if Allocator::allocate(self) {
-                continue;
-            }
-            // dispatch IO and CPU resources to running processes
-            dispatcher.dispatch(self);
+            // service interrupts, then run an OS routine or the current user process
+            interrupts.cycle(self, &mut dispatcher);
 
             // check if we should print info for this cycle
-            if self.master_clock % every_n == 0 {
-                self.print_info();
+            if self.print_detail && every_n > 0 && self.master_clock % every_n == 0 {
+                self.print_info(&dispatcher, &interrupts);
             }
 
             // check if simulation is finished
             if self.running_processes.is_empty() && self.input_queue.is_empty() {
                 println!(
-                    "OS simulation finished at clock time {}.",
-                    self.master_clock
+                    "OS simulation finished at clock time {}. Total OS overhead: {} units.",
+                    self.master_clock, self.os_overhead
                 );
                 break;
             }
         }
     }
 
-    /** Print running process info */
-    fn print_info(&self) {
-        println!(
-            "==================================={}===================================",
-            self.master_clock,
+    /** Position of a process in the ready list, starting at 1 */
+    fn ready_position(&self, pid: PID) -> Option<usize> {
+        let position = match self.input_params.pro_algorithm {
+            Algorithm::MLFQ => self.feedback_queue.position(pid),
+            _ => self.ready_queue.iter().position(|other| *other == pid),
+        };
+        position.map(|idx| idx + 1)
+    }
+
+    /** Print running process info, as the print_3 detail table */
+    fn print_info(&self, dispatcher: &Dispatcher, interrupts: &InterruptController) {
+        let banner = format!(
+            "=================================== {} ===================================",
+            self.master_clock
         );
+        println!("{}", banner);
+        println!("Pid  Program                Memory           Current     Rdy   I/O    Event  ");
+        println!("     Name                   Usage            State       List  T-Dv#  Info   ");
+        println!("---  --------  ----------------------------  ----------  ----  -----  -------");
         for process in sorted(self.running_processes.values()) {
-            println!("{}", process);
+            let row = format!(
+                "{}{:<6}{:<7}{}",
+                process,
+                self.ready_position(process.pid)
+                    .map(|pos| pos.to_string())
+                    .unwrap_or_default(),
+                self.devices.status_of(process.pid),
+                dispatcher.pending_event(process.pid).unwrap_or_default()
+            );
+            println!("{}", row.trim_end());
         }
-        println!(
-            "==================================={}===================================",
-            self.master_clock,
-        );
+        println!();
+
+        // free memory, and any other memory model or scheduler detail
+        let mut details = self.memory.to_string();
+        if self.input_params.pro_algorithm == Algorithm::MLFQ {
+            details.push_str(&self.feedback_queue.to_string());
+        }
+        for line in details.lines() {
+            println!("     {}", line.trim_end());
+        }
+        // what the CPU does next, unless it's a user process
+        match interrupts.next_routine(self, dispatcher) {
+            Some(routine) => println!("     {:<36}Execute Next", routine),
+            None if !dispatcher.is_executing() => println!("     {:<36}Execute Next", "IDLE"),
+            None => {}
+        }
+        println!("{}", banner);
+        println!();
     }
 }
@@ -0,0 +1,179 @@
+use crate::os::memory::AllocResult::{self, *};
+use crate::os::memory::{MemoryManager, MemoryRange};
+use crate::os::process::PID;
+use crate::records::ProcessData;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+/** A process's page table: page n of the process is in frame frames[n] */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PageTable {
+    pub frames: Vec<i32>,
+    pub bytes: i32, // bytes the process asked for; the last page may not be full
+}
+
+/** An entry of the frame table: the page held in a physical frame */
+#[derive(Debug, Copy, Clone)]
+struct Frame {
+    pid: PID,
+    page: i32,
+}
+
+/** Paged memory. Processes are split into pages, each put in whatever physical frame is
+at the front of the free-frame list. Pages and frames are numbered from 0 */
+pub struct PagedMemory {
+    pub page_size: i32,    // bytes per page, and per frame
+    pub total_frames: i32, // number of physical frames
+    free_frames: VecDeque<i32>,
+    frame_table: Vec<Option<Frame>>, // what is in each frame; None if it's free
+    page_tables: BTreeMap<PID, PageTable>, // page table of each process in memory
+}
+
+impl PagedMemory {
+    pub fn new(page_size: i32, total_frames: i32) -> Self {
+        Self {
+            page_size,
+            total_frames,
+            free_frames: (0..total_frames.max(0)).collect(),
+            frame_table: vec![None; total_frames.max(0) as usize],
+            page_tables: BTreeMap::new(),
+        }
+    }
+
+    /** Number of pages needed for `bytes` of memory, rounding up to a whole page */
+    fn pages_needed(&self, bytes: i32) -> i32 {
+        let page_size = self.page_size.max(1);
+        bytes / page_size + i32::from(bytes % page_size != 0)
+    }
+}
+
+impl MemoryManager for PagedMemory {
+    /** Builds a page table for a process from frames on the free-frame list */
+    fn allocate(&mut self, pid: PID, info: &ProcessData) -> AllocResult {
+        // check if this process will even fit in our total memory
+        let bytes = match info.memsize_bytes() {
+            Some(bytes) => bytes,
+            None => return TooBig,
+        };
+        let needed = self.pages_needed(bytes);
+        if needed > self.total_frames {
+            return TooBig;
+        }
+        if needed as usize > self.free_frames.len() {
+            return NoSpace;
+        }
+        let frames: Vec<i32> = self.free_frames.drain(..needed as usize).collect();
+        for (page, frame) in frames.iter().enumerate() {
+            self.frame_table[*frame as usize] = Some(Frame {
+                pid,
+                page: page as i32,
+            });
+        }
+        let table = PageTable { frames, bytes };
+        self.page_tables.insert(pid, table.clone());
+        Allocated(MemoryRange::Pages(table))
+    }
+
+    /** Puts every frame in the process's page table back on the end of the free-frame list */
+    fn free(&mut self, pid: PID, range: &MemoryRange) {
+        self.page_tables.remove(&pid);
+        if let MemoryRange::Pages(table) = range {
+            for frame in &table.frames {
+                self.frame_table[*frame as usize] = None;
+                self.free_frames.push_back(*frame);
+            }
+        }
+    }
+}
+
+/** The frame table, the free-frame list and how full each process's pages are */
+impl fmt::Display for PagedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Frame table ({} frames of {} bytes, Frame: PID#/Page):",
+            self.total_frames, self.page_size
+        )?;
+        let entries: Vec<String> = self
+            .frame_table
+            .iter()
+            .enumerate()
+            .map(|(frame, entry)| match entry {
+                Some(Frame { pid, page }) => {
+                    format!("{:>3}: {:<7}", frame, format!("{}/{}", pid, page))
+                }
+                None => format!("{:>3}: {:<7}", frame, "-"),
+            })
+            .collect();
+        for row in entries.chunks(6) {
+            writeln!(f, "  {}", row.concat())?;
+        }
+        let free: Vec<String> = self
+            .free_frames
+            .iter()
+            .map(|frame| frame.to_string())
+            .collect();
+        writeln!(f, "Free frames: {}", free.join(" "))?;
+
+        // how full the pages of each process are; only the last page can have room left
+        for (pid, table) in &self.page_tables {
+            let size = table.frames.len() as i32 * self.page_size;
+            let percent = if size > 0 {
+                100.0 * f64::from(table.bytes) / f64::from(size)
+            } else {
+                0.0
+            };
+            writeln!(
+                f,
+                "Page table PID#: {}  {} pages, {} of {} bytes used ({:.1}%)",
+                pid,
+                table.frames.len(),
+                table.bytes,
+                size,
+                percent
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames_of(result: AllocResult) -> Vec<i32> {
+        match result {
+            Allocated(MemoryRange::Pages(table)) => table.frames,
+            _ => panic!("expected a page table"),
+        }
+    }
+
+    #[test]
+    fn pages_are_rounded_up_and_frames_come_from_the_free_list() {
+        let mut memory = PagedMemory::new(4000, 10);
+        // 10 KB needs 3 pages of 4000 bytes
+        let first = frames_of(memory.allocate(1, &ProcessData::with_memsize(10)));
+        assert_eq!(first, vec![0, 1, 2]);
+        let second = frames_of(memory.allocate(2, &ProcessData::with_memsize(16)));
+        assert_eq!(second, vec![3, 4, 5, 6]);
+        assert!(matches!(
+            memory.allocate(3, &ProcessData::with_memsize(44)),
+            TooBig
+        ));
+        assert!(matches!(
+            memory.allocate(3, &ProcessData::with_memsize(16)),
+            NoSpace
+        ));
+
+        // freed frames go on the end of the free-frame list, after 7 8 9
+        let table = PageTable {
+            frames: first,
+            bytes: 10000,
+        };
+        memory.free(1, &MemoryRange::Pages(table));
+        let third = frames_of(memory.allocate(3, &ProcessData::with_memsize(20)));
+        assert_eq!(third, vec![7, 8, 9, 0, 1]);
+        assert!(memory.frame_table[2].is_none());
+        assert_eq!(memory.free_frames, vec![2]);
+    }
+}
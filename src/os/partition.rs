@@ -0,0 +1,377 @@
+use crate::os::memory::AllocResult::{self, *};
+use crate::os::memory::{MemoryManager, MemoryRange};
+use crate::os::process::PID;
+use crate::records::ProcessData;
+use std::collections::HashMap;
+use std::fmt;
+
+/** Placement policy used to choose a hole for a new variable partition */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FitPolicy {
+    First, // lowest addressed hole that is big enough
+    Best,  // smallest hole that is big enough
+    Worst, // largest hole
+    Next,  // first fit, starting from where the last allocation ended
+}
+
+/** A free region of memory: (first byte, size in bytes) */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Hole {
+    start: i32,
+    size: i32,
+}
+
+/** Variable-partition memory with a byte-granular free list.
+Holes are kept sorted by address and adjacent holes are merged when memory is freed */
+pub struct VariablePartitions {
+    pub policy: FitPolicy,
+    pub total_size: i32,         // total bytes of memory
+    pub max_size: i32,           // largest partition a single process may have
+    pub compact_at: Option<f64>, // external fragmentation % that allows compaction, if ever
+    free_list: Vec<Hole>,
+    next_fit: i32, // address just past the last allocation, for Next fit
+}
+
+impl VariablePartitions {
+    pub fn new(total_size: i32, max_size: i32, policy: FitPolicy, compact_at: Option<f64>) -> Self {
+        let total_size = total_size.max(0);
+        Self {
+            policy,
+            total_size,
+            max_size,
+            compact_at,
+            free_list: if total_size > 0 {
+                vec![Hole {
+                    start: 0,
+                    size: total_size,
+                }]
+            } else {
+                Vec::new()
+            },
+            next_fit: 0,
+        }
+    }
+
+    /** Checks if a partition of this size could ever be allocated */
+    pub fn fits(&self, size: i32) -> bool {
+        size <= self.total_size && (self.max_size <= 0 || size <= self.max_size)
+    }
+
+    /** Allocates a partition of `size` bytes using the placement policy.
+    @returns
+    Some(first byte) of the new partition
+    None if no single hole is big enough */
+    pub fn allocate(&mut self, size: i32) -> Option<i32> {
+        let candidates = self
+            .free_list
+            .iter()
+            .enumerate()
+            .filter(|(_, hole)| hole.size >= size);
+        let idx = match self.policy {
+            FitPolicy::First => candidates.map(|(idx, _)| idx).next(),
+            FitPolicy::Best => candidates.min_by_key(|(_, hole)| hole.size).map(|(i, _)| i),
+            FitPolicy::Worst => candidates.max_by_key(|(_, hole)| hole.size).map(|(i, _)| i),
+            FitPolicy::Next => {
+                let next_fit = self.next_fit;
+                let (after, before): (Vec<_>, Vec<_>) =
+                    candidates.partition(|(_, hole)| hole.start + hole.size > next_fit);
+                after.into_iter().chain(before).map(|(idx, _)| idx).next()
+            }
+        }?;
+
+        let hole = &mut self.free_list[idx];
+        // Next fit may start partway into the hole the cursor is in
+        let start = if self.policy == FitPolicy::Next
+            && hole.start < self.next_fit
+            && hole.start + hole.size - self.next_fit >= size
+        {
+            self.next_fit
+        } else {
+            hole.start
+        };
+        let hole = *hole;
+        self.free_list.remove(idx);
+        // put back whatever is left of the hole on either side of the new partition
+        let mut insert_at = idx;
+        if start > hole.start {
+            self.free_list.insert(
+                insert_at,
+                Hole {
+                    start: hole.start,
+                    size: start - hole.start,
+                },
+            );
+            insert_at += 1;
+        }
+        let end = start + size;
+        if end < hole.start + hole.size {
+            self.free_list.insert(
+                insert_at,
+                Hole {
+                    start: end,
+                    size: hole.start + hole.size - end,
+                },
+            );
+        }
+        self.next_fit = end % self.total_size.max(1);
+        Some(start)
+    }
+
+    /** Returns a partition to the free list, merging it with neighbouring holes */
+    pub fn free(&mut self, start: i32, size: i32) {
+        let idx = self
+            .free_list
+            .iter()
+            .position(|hole| hole.start > start)
+            .unwrap_or(self.free_list.len());
+        self.free_list.insert(idx, Hole { start, size });
+
+        // merge with the following hole
+        if idx + 1 < self.free_list.len() {
+            let next = self.free_list[idx + 1];
+            if start + size == next.start {
+                self.free_list[idx].size += next.size;
+                self.free_list.remove(idx + 1);
+            }
+        }
+        // merge with the preceding hole
+        if idx > 0 {
+            let prev = self.free_list[idx - 1];
+            if prev.start + prev.size == start {
+                self.free_list[idx - 1].size += self.free_list[idx].size;
+                self.free_list.remove(idx);
+            }
+        }
+    }
+
+    /** Total free bytes, across every hole */
+    pub fn free_bytes(&self) -> i32 {
+        self.free_list.iter().map(|hole| hole.size).sum()
+    }
+
+    /** Size of the largest hole in bytes */
+    pub fn largest_hole(&self) -> i32 {
+        self.free_list
+            .iter()
+            .map(|hole| hole.size)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /** External fragmentation as a percentage: the share of free memory
+    that is not part of the largest hole, so can't be used for the biggest request */
+    pub fn external_fragmentation(&self) -> f64 {
+        let free = self.free_bytes();
+        if free == 0 {
+            return 0.0;
+        }
+        100.0 * f64::from(free - self.largest_hole()) / f64::from(free)
+    }
+
+    /** Bytes of partitions that sit after the first hole. Compaction slides
+    every one of them down towards address 0, so this is how much it moves */
+    pub fn bytes_to_move(&self) -> i32 {
+        match self.free_list.first() {
+            Some(first) => self.total_size - first.start - self.free_bytes(),
+            None => 0,
+        }
+    }
+}
+
+impl MemoryManager for VariablePartitions {
+    /** Carves a variable partition out of the free list for a process.
+    Process memory sizes are in KB, partitions are in bytes */
+    fn allocate(&mut self, _pid: PID, info: &ProcessData) -> AllocResult {
+        // check if this process will ever fit in a partition
+        let proc_mem_size = match info.memsize_bytes() {
+            Some(size) if self.fits(size) => size,
+            _ => return TooBig,
+        };
+        match VariablePartitions::allocate(self, proc_mem_size) {
+            Some(start) => Allocated(MemoryRange::Bytes(start, start + proc_mem_size - 1)),
+            None => NoSpace,
+        }
+    }
+
+    fn free(&mut self, _pid: PID, range: &MemoryRange) {
+        if let MemoryRange::Bytes(first, last) = range {
+            VariablePartitions::free(self, *first, last - first + 1);
+        }
+    }
+
+    /** Compaction helps when there's enough free memory for the process in total,
+    but no single hole is big enough, and memory is fragmented past the threshold */
+    fn compaction_helps(&self, info: &ProcessData) -> bool {
+        let size = match info.memsize_bytes() {
+            Some(size) => size,
+            None => return false,
+        };
+        match self.compact_at {
+            Some(threshold) => {
+                self.largest_hole() < size
+                    && self.free_bytes() >= size
+                    && self.external_fragmentation() >= threshold
+            }
+            None => false,
+        }
+    }
+
+    fn compaction_bytes(&self) -> i32 {
+        self.bytes_to_move()
+    }
+
+    /** Slides every partition down towards address 0, in address order,
+    leaving a single hole at the top of memory */
+    fn compact(&mut self, memory_map: &mut HashMap<PID, MemoryRange>) -> i32 {
+        // (first byte, size) of every partition, in address order
+        let mut partitions: Vec<(i32, i32, PID)> = memory_map
+            .iter()
+            .filter_map(|(pid, range)| match range {
+                MemoryRange::Bytes(first, last) => Some((*first, last - first + 1, *pid)),
+                _ => None,
+            })
+            .collect();
+        partitions.sort();
+
+        let mut next_start = 0;
+        let mut moved = 0;
+        for (first, size, pid) in partitions {
+            if first != next_start {
+                memory_map.insert(pid, MemoryRange::Bytes(next_start, next_start + size - 1));
+                moved += size;
+            }
+            next_start += size;
+        }
+        self.free_list.clear();
+        if next_start < self.total_size {
+            self.free_list.push(Hole {
+                start: next_start,
+                size: self.total_size - next_start,
+            });
+        }
+        self.next_fit = next_start % self.total_size.max(1);
+        moved
+    }
+}
+
+impl fmt::Display for VariablePartitions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let holes: Vec<String> = self
+            .free_list
+            .iter()
+            .map(|hole| format!("{}-{}", hole.start, hole.start + hole.size - 1))
+            .collect();
+        writeln!(f, "Free holes ({:?}):\t{}", self.policy, holes.join(" "))?;
+        writeln!(
+            f,
+            "External fragmentation: {} bytes free in {} holes, largest hole {} bytes ({:.1}%)",
+            self.free_bytes(),
+            self.free_list.len(),
+            self.largest_hole(),
+            self.external_fragmentation()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** Memory with free holes of 25000 bytes at 0, 30000 at 40000 and 20000 at 80000,
+    and the Next fit cursor at 80000, where the last allocation ended */
+    fn fragmented(policy: FitPolicy) -> VariablePartitions {
+        let mut memory = VariablePartitions::new(100000, 0, policy, None);
+        for size in [25000, 5000, 10000, 30000, 10000] {
+            memory.allocate(size);
+        }
+        memory.free(0, 25000);
+        memory.free(40000, 30000);
+        memory
+    }
+
+    #[test]
+    fn first_fit_takes_the_lowest_hole_big_enough() {
+        assert_eq!(fragmented(FitPolicy::First).allocate(15000), Some(0));
+    }
+
+    #[test]
+    fn best_fit_takes_the_smallest_hole_big_enough() {
+        assert_eq!(fragmented(FitPolicy::Best).allocate(15000), Some(80000));
+    }
+
+    #[test]
+    fn worst_fit_takes_the_largest_hole() {
+        assert_eq!(fragmented(FitPolicy::Worst).allocate(15000), Some(40000));
+    }
+
+    #[test]
+    fn next_fit_starts_from_the_cursor_and_wraps_around() {
+        let mut memory = fragmented(FitPolicy::Next);
+        assert_eq!(memory.allocate(15000), Some(80000));
+        // the 5000 bytes left after the cursor are too small, so it wraps to the lowest hole
+        assert_eq!(memory.allocate(15000), Some(0));
+        assert_eq!(memory.allocate(15000), Some(40000));
+    }
+
+    #[test]
+    fn next_fit_can_start_partway_into_a_hole() {
+        let mut memory = VariablePartitions::new(100000, 0, FitPolicy::Next, None);
+        assert_eq!(memory.allocate(10000), Some(0));
+        memory.free(0, 10000);
+        // memory is one hole again, but the cursor is still at 10000
+        assert_eq!(memory.allocate(10000), Some(10000));
+        assert_eq!(
+            memory.free_list[0],
+            Hole {
+                start: 0,
+                size: 10000
+            }
+        );
+        assert_eq!(memory.allocate(10000), Some(20000));
+    }
+
+    #[test]
+    fn compaction_merges_holes_and_moves_partitions_down() {
+        let mut memory = VariablePartitions::new(100000, 0, FitPolicy::First, Some(10.0));
+        let mut memory_map = HashMap::new();
+        for pid in 1..=3 {
+            if let Allocated(range) =
+                MemoryManager::allocate(&mut memory, pid, &ProcessData::with_memsize(30))
+            {
+                memory_map.insert(pid, range);
+            }
+        }
+        for pid in [1, 3] {
+            let range = memory_map.remove(&pid).unwrap();
+            MemoryManager::free(&mut memory, pid, &range);
+        }
+        // 70000 bytes free, but the largest hole is only 40000
+        assert!(matches!(
+            MemoryManager::allocate(&mut memory, 4, &ProcessData::with_memsize(50)),
+            NoSpace
+        ));
+        assert!(memory.compaction_helps(&ProcessData::with_memsize(50)));
+        assert!(!memory.compaction_helps(&ProcessData::with_memsize(40)));
+        assert_eq!(memory.compaction_bytes(), 30000);
+
+        assert_eq!(memory.compact(&mut memory_map), 30000);
+        assert!(matches!(memory_map[&2], MemoryRange::Bytes(0, 29999)));
+        assert_eq!(memory.largest_hole(), 70000);
+        assert_eq!(memory.compaction_bytes(), 0);
+        assert!(matches!(
+            MemoryManager::allocate(&mut memory, 4, &ProcessData::with_memsize(50)),
+            Allocated(MemoryRange::Bytes(30000, 79999))
+        ));
+    }
+
+    #[test]
+    fn no_compaction_without_a_threshold() {
+        let mut memory = VariablePartitions::new(100000, 0, FitPolicy::First, None);
+        let first = MemoryManager::allocate(&mut memory, 1, &ProcessData::with_memsize(30));
+        MemoryManager::allocate(&mut memory, 2, &ProcessData::with_memsize(30));
+        if let Allocated(range) = first {
+            MemoryManager::free(&mut memory, 1, &range);
+        }
+        assert!(!memory.compaction_helps(&ProcessData::with_memsize(50)));
+    }
+}
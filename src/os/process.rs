@@ -11,8 +11,9 @@ pub struct ProcessControlBlock {
     pub pid: PID,
     pub clk: i32, // current CPU clock
     pub state: ProcessState,
-    pub total_cpu: i32, // total CPU cycles completed
-    pub total_ios: i32, // total IO cycles completed
+    pub total_cpu: i32,        // total CPU cycles completed
+    pub total_ios: i32,        // total IO cycles completed
+    pub context_switches: i32, // number of times this process was switched onto the CPU
     pub start_time: i32,
     pub end_time: i32,
     pub memory_map: MemoryRange, // where in memory this process is located
@@ -37,13 +38,74 @@ impl PartialEq for ProcessControlBlock {
     }
 }
 
+/** The Pid, Program Name, Memory Usage and Current State columns of the print_3 detail table */
 impl fmt::Display for ProcessControlBlock {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let pid = self.pid;
         let name = &self.info.process_name;
-        let state = &self.state;
-        let blocks = &self.memory_map;
-        write!(f, "{}\t{}\t{}\t\t\t{:?}", pid, name, blocks, state)
+        let state = format!("{:?}", self.state);
+        let blocks = self.memory_map.to_string();
+        write!(
+            f,
+            "{:<5}{:<10}{:<30}{:<12}",
+            pid,
+            name,
+            blocks.trim_end(),
+            state
+        )
+    }
+}
+
+/** Accounting for a process, taken when it is de-allocated */
+#[derive(Clone)]
+pub struct ProcessSummary {
+    pub pid: PID,
+    pub name: String,
+    pub service_units: i32, // total IO units
+    pub cpu_units: i32,     // total CPU units
+    pub start_time: i32,
+    pub end_time: i32,
+    pub dealloc_time: i32,
+    pub context_switches: i32,
+    pub memory_map: MemoryRange,
+}
+
+impl ProcessSummary {
+    pub fn new(proc: &ProcessControlBlock, dealloc_time: i32) -> Self {
+        Self {
+            pid: proc.pid,
+            name: proc.info.process_name.clone(),
+            service_units: proc.total_ios,
+            cpu_units: proc.total_cpu,
+            start_time: proc.start_time,
+            end_time: proc.end_time,
+            dealloc_time,
+            context_switches: proc.context_switches,
+            memory_map: proc.memory_map.clone(),
+        }
+    }
+
+    /** Clock units from when the process first started to when it completed */
+    pub fn elapsed(&self) -> i32 {
+        self.end_time - self.start_time
+    }
+}
+
+impl fmt::Display for ProcessSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "De-Allocated: {} at MC time {}",
+            self.name, self.dealloc_time
+        )?;
+        writeln!(f, "  Total Service Units: {}", self.service_units)?;
+        writeln!(f, "  Total CPU Units    : {}", self.cpu_units)?;
+        writeln!(f, "  MC Start Time      : {}", self.start_time)?;
+        writeln!(f, "  MC End Time        : {}", self.end_time)?;
+        writeln!(f, "  Total Elapsed Units: {}", self.elapsed())?;
+        writeln!(f, "  Context Switches   : {}", self.context_switches)?;
+        let blocks = self.memory_map.to_string();
+        writeln!(f, "  Memory Blks Used   :  {}", blocks.trim_end())
     }
 }
 
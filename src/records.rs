@@ -1,17 +1,15 @@
-use libc::{c_char, c_int};
-use std::fs::File;
-use std::io::{Read, Result, Seek, SeekFrom};
-use std::mem;
-
-use cute::c;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::str::FromStr;
 
 /** Program input params */
 #[derive(Debug, Clone)]
 pub struct OSParams {
     pub mem_model: MemModel,
     pub pro_algorithm: Algorithm,
-    pub mem_fix_block_size: i32,   /* F: Number of bytes per block  */
-    pub mem_fix_total_blocks: i32, /* F: Number of available blocks */
+    pub mem_fix_block_size: i32,   /* F,P: Bytes per block or page  */
+    pub mem_fix_total_blocks: i32, /* F,P: Available blocks/frames  */
     pub mem_var_maxsize: i32,      /* V: Maximum block size         */
     pub mem_var_totsize: i32,      /* V: Total available memory     */
     pub pro_max_tasks: i32,        /* Maximum Processes allowed     */
@@ -23,14 +21,15 @@ pub struct OSParams {
                                    /*   output every n units        */
 }
 /** Enums for OSParams */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum MemModel {
     None,
     Fixed,
     Variable,
+    Paged,
     Unknown,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Algorithm {
     FIFO,
     IPRI,
@@ -41,6 +40,23 @@ pub enum Algorithm {
 /* IPRI : initial priority       */
 /* MLFQ : multi-level fb queue   */
 
+/** Size in bytes of one OS_OSP.DAT record */
+pub const PARAMS_RECORD_SIZE: usize = 52;
+/** Size in bytes of one OS_INP.DAT record */
+pub const INPUT_RECORD_SIZE: usize = 136;
+/** Run info rows in each OS_INP.DAT record */
+pub const RUN_INFO_ROWS: usize = 10;
+/** First bytes of an extended input file, which has no limit on run info rows */
+pub const EXTENDED_MAGIC: &[u8; 4] = b"OSIX";
+/** Extended input format version written by this simulator */
+pub const EXTENDED_VERSION: i32 = 1;
+/** Sizes in bytes of the extended input file header, record header and run info row */
+const EXTENDED_HEADER_SIZE: usize = 16;
+const EXTENDED_RECORD_SIZE: usize = 20;
+const EXTENDED_BURST_SIZE: usize = 12;
+/** Most processes an input file may hold, as MAX_SIZE in OS_DS.H */
+pub const MAX_SIZE: usize = 32;
+
 /**
 Input info for a single process
 */
@@ -61,7 +77,7 @@ pub struct RunInfo {
     pub IO_device_type: IODeviceType,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum IODeviceType {
     Disk,
     Tape,
@@ -69,115 +85,897 @@ pub enum IODeviceType {
     Unknown,
 }
 
-/** Utility function for converting 8 byte c_char arrays to str */
-fn convert_bytes(buf: &[c_char; 8]) -> String {
-    let mut value = String::new();
-    for chr in buf.iter() {
-        let chr = *chr as u8 as char;
-        if chr != '\u{0}' {
-            // ignore c-string newline
-            value.push(chr);
+impl MemModel {
+    /** Letter used for the memory model in the text tables */
+    pub fn letter(&self) -> &'static str {
+        match self {
+            MemModel::None => "N",
+            MemModel::Fixed => "F",
+            MemModel::Variable => "V",
+            MemModel::Paged => "P",
+            MemModel::Unknown => "?",
+        }
+    }
+}
+
+/** Parses a memory model from its .DAT code, its text table letter or its name */
+impl FromStr for MemModel {
+    type Err = String;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "0" | "n" | "none" => Ok(MemModel::None),
+            "1" | "f" | "fixed" => Ok(MemModel::Fixed),
+            "2" | "v" | "variable" => Ok(MemModel::Variable),
+            "3" | "p" | "paged" => Ok(MemModel::Paged),
+            _ => Err(format!("unknown memory model '{}'", value)),
+        }
+    }
+}
+
+/** Parses an algorithm name, ignoring case and padding */
+impl FromStr for Algorithm {
+    type Err = String;
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.trim().to_uppercase().as_str() {
+            "FIFO" => Ok(Algorithm::FIFO),
+            "IPRI" => Ok(Algorithm::IPRI),
+            "MLFQ" => Ok(Algorithm::MLFQ),
+            _ => Err(format!("unknown algorithm '{}'", value)),
+        }
+    }
+}
+
+impl IODeviceType {
+    /** Device type for a code used in the .DAT files. 0 is a CPU burst with no IO */
+    pub fn from_code(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(IODeviceType::Unknown),
+            1 => Some(IODeviceType::Disk),
+            2 => Some(IODeviceType::Tape),
+            3 => Some(IODeviceType::CD),
+            _ => None,
+        }
+    }
+    /** Device type code used in the .DAT files and the T-Dv# report column */
+    pub fn code(self) -> i32 {
+        match self {
+            IODeviceType::Disk => 1,
+            IODeviceType::Tape => 2,
+            IODeviceType::CD => 3,
+            IODeviceType::Unknown => 0,
+        }
+    }
+}
+
+/** Errors reading or writing workload records */
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    // the file ends partway through a record
+    Truncated {
+        offset: usize, // byte offset the partial record starts at
+        len: usize,    // bytes of it in the file
+        expected: usize,
+    },
+    // the file holds more records than the simulator allows
+    TooManyRecords {
+        count: usize,
+        max: usize,
+    },
+    // a field holds a value the simulator doesn't understand
+    InvalidField {
+        record: usize, // index of the record in the file, starting at 0
+        field: String,
+        value: String,
+    },
+    // an extended input file header that can't be read
+    Header(String),
+    // a text, TOML or YAML workload file doesn't follow its format
+    Text(String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::Io(e) => write!(f, "{}", e),
+            RecordError::Truncated {
+                offset,
+                len,
+                expected,
+            } => write!(
+                f,
+                "truncated record at byte offset {}: {} of {} bytes",
+                offset, len, expected
+            ),
+            RecordError::TooManyRecords { count, max } => write!(
+                f,
+                "file holds {} records, but at most {} are allowed",
+                count, max
+            ),
+            RecordError::InvalidField {
+                record,
+                field,
+                value,
+            } => write!(
+                f,
+                "record index {}: invalid {} value {}",
+                record, field, value
+            ),
+            RecordError::Header(message) => write!(f, "extended input header: {}", message),
+            RecordError::Text(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<io::Error> for RecordError {
+    fn from(e: io::Error) -> Self {
+        RecordError::Io(e)
+    }
+}
+
+/** print_1: the O/S parameters for a simulation run */
+impl fmt::Display for OSParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "O/S Parameters:")?;
+        writeln!(f)?;
+        writeln!(f, "   Memory Model Used:      {}", self.mem_model)?;
+        writeln!(f, "   Fixed Mem Blk Size:     {}", self.mem_fix_block_size)?;
+        writeln!(
+            f,
+            "   Fixed Mem Blks Aval:    {}",
+            self.mem_fix_total_blocks
+        )?;
+        writeln!(f, "   Variable Mem Max Size:  {}", self.mem_var_maxsize)?;
+        writeln!(f, "   Variable Mem Tot Size:  {}", self.mem_var_totsize)?;
+        writeln!(f, "   Max. Tasks Allowed:     {}", self.pro_max_tasks)?;
+        writeln!(f, "   Process Mgmt Algthm:    {}", self.pro_algorithm)?;
+        writeln!(f, "   Initial Quantum Size:   {}", self.init_quantum)?;
+        writeln!(f, "   Number Disk Units:      {}", self.disk_units)?;
+        writeln!(f, "   Number Tape Units:      {}", self.tape_units)?;
+        writeln!(f, "   Number CD   Units:      {}", self.cdrom_units)?;
+        writeln!(f, "   Print Every N Units:    {}", self.every_n_units)
+    }
+}
+
+impl fmt::Display for MemModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MemModel::None => "None",
+            MemModel::Fixed => "Fixed",
+            MemModel::Variable => "Variable",
+            MemModel::Paged => "Paged",
+            MemModel::Unknown => "Unknown",
+        };
+        f.pad(name)
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Algorithm::FIFO => "FIFO",
+            Algorithm::IPRI => "IPRI",
+            Algorithm::MLFQ => "MLFQ",
+            Algorithm::Unknown => "Unknown",
+        };
+        f.pad(name)
+    }
+}
+
+/** The input data list entry for a process, with one line per run_info field */
+impl fmt::Display for ProcessData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "  Name     : {}", self.process_name)?;
+        writeln!(f, "  Init_Prio: {}", self.process_priority)?;
+        writeln!(f, "  Memsize  : {}", self.process_memsize)?;
+        for (i, info) in self.run_info.iter().enumerate() {
+            writeln!(f, "  i={}, j=0 : {}", i, info.CPU_units)?;
+            writeln!(f, "  i={}, j=1 : {}", i, info.IO_units)?;
+            writeln!(f, "  i={}, j=2 : {}", i, info.IO_device_type.code())?;
+        }
+        Ok(())
+    }
+}
+
+/** Reads the little-endian fields of one .DAT record in order, naming each field
+so a bad value can be reported against the record and field it came from */
+struct RecordDecoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    record: usize, // index of the record in its file, starting at 0
+}
+
+impl<'a> RecordDecoder<'a> {
+    fn new(bytes: &'a [u8], record: usize) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            record,
+        }
+    }
+
+    /** Reads the next 4 byte little-endian c_int */
+    fn int(&mut self) -> i32 {
+        let mut field = [0; 4];
+        field.copy_from_slice(&self.bytes[self.pos..self.pos + 4]);
+        self.pos += 4;
+        i32::from_le_bytes(field)
+    }
+
+    /** Reads the next 8 byte c_char array as a string, up to its first nul */
+    fn name(&mut self) -> String {
+        let field = &self.bytes[self.pos..self.pos + 8];
+        self.pos += 8;
+        let len = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..len]).into_owned()
+    }
+
+    /** Builds one run info row from its (CPU units, IO units, device type) fields.
+    The last CPU burst of a process has no IO, so no device */
+    fn run_info(&self, row: usize, info: [i32; 3]) -> Result<RunInfo, RecordError> {
+        let IO_device_type = match IODeviceType::from_code(info[2]) {
+            Some(IODeviceType::Unknown) if info[1] > 0 => None,
+            device => device,
+        }
+        .ok_or_else(|| self.invalid(&format!("run_info[{}][2]", row), info[2]))?;
+        Ok(RunInfo {
+            CPU_units: info[0],
+            IO_units: info[1],
+            IO_device_type,
+        })
+    }
+
+    fn invalid(&self, field: &str, value: impl fmt::Display) -> RecordError {
+        RecordError::InvalidField {
+            record: self.record,
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+/** The `size` bytes of a file starting at `offset`
+@returns
+Err(RecordError::Truncated) if the file ends before them */
+fn bytes_at(bytes: &[u8], offset: usize, size: usize) -> Result<&[u8], RecordError> {
+    offset
+        .checked_add(size)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or_else(|| RecordError::Truncated {
+            offset,
+            len: bytes.len().saturating_sub(offset),
+            expected: size,
+        })
+}
+
+/** Splits a .DAT file into fixed size records
+@returns
+Err(RecordError::Truncated) if the file ends partway through a record */
+fn split_records(bytes: &[u8], size: usize) -> Result<Vec<&[u8]>, RecordError> {
+    let records = bytes.chunks_exact(size);
+    let remainder = records.remainder();
+    if !remainder.is_empty() {
+        return Err(RecordError::Truncated {
+            offset: bytes.len() - remainder.len(),
+            len: remainder.len(),
+            expected: size,
+        });
+    }
+    Ok(records.collect())
+}
+
+/** Writes the little-endian fields of one .DAT record in order; the counterpart of RecordDecoder */
+struct RecordEncoder {
+    bytes: Vec<u8>,
+    record: usize, // index of the record in its file, starting at 0
+}
+
+impl RecordEncoder {
+    fn new(size: usize, record: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(size),
+            record,
+        }
+    }
+
+    /** Writes a 4 byte little-endian c_int */
+    fn int(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /** Writes an 8 byte c_char array: up to 7 characters, padded with `pad`
+    and always ending in a nul */
+    fn name(&mut self, field: &str, value: &str, pad: u8) -> Result<(), RecordError> {
+        if value.len() > 7 || value.bytes().any(|b| b == 0) {
+            return Err(self.invalid(field, format!("{:?}", value)));
+        }
+        let mut name = [pad; 8];
+        name[..value.len()].copy_from_slice(value.as_bytes());
+        name[7] = 0;
+        self.bytes.extend_from_slice(&name);
+        Ok(())
+    }
+
+    fn invalid(&self, field: &str, value: impl fmt::Display) -> RecordError {
+        RecordError::InvalidField {
+            record: self.record,
+            field: field.to_string(),
+            value: value.to_string(),
         }
     }
-    value
 }
 
 impl OSParams {
-    pub fn read_from_file(filename: &str) -> Result<OSParams> {
-        //internal model of c struct read from .DAT file
-        #[repr(C)]
-        struct OSParamsInternal {
-            mem_model: c_int,
-            mem_fix_blksize: c_int,
-            mem_fix_blksaveal: c_int,
-            mem_var_maxsize: c_int,
-            mem_var_totsize: c_int,
-            pro_max_tasks: c_int,
-            init_quantum: c_int,
-            disk_units: c_int,
-            tape_units: c_int,
-            cdrom_units: c_int,
-            every_n_units: c_int,
-            pro_algorithm: [c_char; 8],
-        }
-
-        let mut file = File::open(filename)?;
-        let mut data: [u8; 52] = [0; 52];
-        file.read_exact(&mut data)?;
-        let params: OSParamsInternal = unsafe { mem::transmute(data) };
-
-        // parse the c struct values into rust struct
+    /** FIFO scheduling of fixed memory blocks, with one unit of each device and room
+    for MAX_SIZE processes. Every field passes validation */
+    #[cfg(test)]
+    pub fn with_fixed_blocks(block_size: i32, total_blocks: i32) -> Self {
+        Self {
+            mem_model: MemModel::Fixed,
+            pro_algorithm: Algorithm::FIFO,
+            mem_fix_block_size: block_size,
+            mem_fix_total_blocks: total_blocks,
+            mem_var_maxsize: 0,
+            mem_var_totsize: 0,
+            pro_max_tasks: MAX_SIZE as i32,
+            init_quantum: 10,
+            disk_units: 1,
+            tape_units: 1,
+            cdrom_units: 1,
+            every_n_units: 100,
+        }
+    }
+
+    /** Reads only the first parameter record in the file */
+    pub fn read_from_file(filename: &str) -> Result<OSParams, RecordError> {
+        let mut data = [0; PARAMS_RECORD_SIZE];
+        File::open(filename)?.read_exact(&mut data)?;
+        OSParams::read_one_entry(&data, 0)
+    }
+    /** Reads every parameter record in the file; each record is a separate OS run */
+    pub fn read_all_from_file(filename: &str) -> Result<Vec<OSParams>, RecordError> {
+        let bytes = fs::read(filename)?;
+        split_records(&bytes, PARAMS_RECORD_SIZE)?
+            .into_iter()
+            .enumerate()
+            .map(|(idx, record)| OSParams::read_one_entry(record, idx))
+            .collect()
+    }
+    /** Writes parameter records to a file, replacing its contents */
+    pub fn write_to_file(filename: &str, params: &[OSParams]) -> Result<(), RecordError> {
+        let mut bytes = Vec::with_capacity(params.len() * PARAMS_RECORD_SIZE);
+        for (idx, record) in params.iter().enumerate() {
+            bytes.extend(record.write_one_entry(idx)?);
+        }
+        File::create(filename)?.write_all(&bytes)?;
+        Ok(())
+    }
+    /** Encodes one os_parm_record, the inverse of read_one_entry */
+    fn write_one_entry(&self, idx: usize) -> Result<Vec<u8>, RecordError> {
+        let mut fields = RecordEncoder::new(PARAMS_RECORD_SIZE, idx);
+        fields.int(match self.mem_model {
+            MemModel::None => 0,
+            MemModel::Fixed => 1,
+            MemModel::Variable => 2,
+            MemModel::Paged => 3,
+            MemModel::Unknown => return Err(fields.invalid("mem_model", &self.mem_model)),
+        });
+        fields.int(self.mem_fix_block_size);
+        fields.int(self.mem_fix_total_blocks);
+        fields.int(self.mem_var_maxsize);
+        fields.int(self.mem_var_totsize);
+        fields.int(self.pro_max_tasks);
+        fields.int(self.init_quantum);
+        fields.int(self.disk_units);
+        fields.int(self.tape_units);
+        fields.int(self.cdrom_units);
+        fields.int(self.every_n_units);
+        if self.pro_algorithm == Algorithm::Unknown {
+            return Err(fields.invalid("pro_algorithm", &self.pro_algorithm));
+        }
+        fields.name("pro_algorithm", &self.pro_algorithm.to_string(), b' ')?;
+        Ok(fields.bytes)
+    }
+    /** Decodes one os_parm_record: 11 c_ints then the 8 char algorithm name */
+    fn read_one_entry(record: &[u8], idx: usize) -> Result<OSParams, RecordError> {
+        let mut fields = RecordDecoder::new(record, idx);
+        let mem_model = match fields.int() {
+            0 => MemModel::None,
+            1 => MemModel::Fixed,
+            2 => MemModel::Variable,
+            3 => MemModel::Paged,
+            code => return Err(fields.invalid("mem_model", code)),
+        };
+        let mem_fix_block_size = fields.int();
+        let mem_fix_total_blocks = fields.int();
+        let mem_var_maxsize = fields.int();
+        let mem_var_totsize = fields.int();
+        let pro_max_tasks = fields.int();
+        let init_quantum = fields.int();
+        let disk_units = fields.int();
+        let tape_units = fields.int();
+        let cdrom_units = fields.int();
+        let every_n_units = fields.int();
+        // algorithm names are space padded in the .DAT file, e.g. "FIFO   "
+        let name = fields.name();
+        let pro_algorithm = name
+            .parse()
+            .map_err(|_| fields.invalid("pro_algorithm", format!("{:?}", name)))?;
+
         Ok(OSParams {
-            // auto convert c_int -> i32
-            mem_fix_block_size: params.mem_fix_blksize,
-            mem_fix_total_blocks: params.mem_fix_blksaveal,
-            mem_var_maxsize: params.mem_var_maxsize,
-            mem_var_totsize: params.mem_var_totsize,
-            pro_max_tasks: params.pro_max_tasks,
-            init_quantum: params.init_quantum,
-            disk_units: params.disk_units,
-            tape_units: params.tape_units,
-            cdrom_units: params.cdrom_units,
-            every_n_units: params.every_n_units,
-            // match other values
-            mem_model: match params.mem_model {
-                0 => MemModel::None,
-                1 => MemModel::Fixed,
-                2 => MemModel::Variable,
-                _ => MemModel::Unknown,
-            },
-            pro_algorithm: match convert_bytes(&params.pro_algorithm).as_str() {
-                "FIFO" => Algorithm::FIFO,
-                "IPRI" => Algorithm::IPRI,
-                "MLFQ" => Algorithm::MLFQ,
-                _ => Algorithm::Unknown,
-            },
+            mem_model,
+            pro_algorithm,
+            mem_fix_block_size,
+            mem_fix_total_blocks,
+            mem_var_maxsize,
+            mem_var_totsize,
+            pro_max_tasks,
+            init_quantum,
+            disk_units,
+            tape_units,
+            cdrom_units,
+            every_n_units,
         })
     }
 }
 impl ProcessData {
-    pub fn read_from_file(filename: &str, num_entries: u32) -> Result<Vec<ProcessData>> {
-        let mut file = File::open(filename)?;
-        let mut data: Vec<ProcessData> = Vec::new();
-        for i in 1..num_entries + 1 {
-            data.push(ProcessData::read_one_entry(&mut file)?);
-            file.seek(SeekFrom::Start((i * 136) as u64))?;
-        }
-        Ok(data)
-    }
-    fn read_one_entry(file: &mut File) -> Result<ProcessData> {
-        #[repr(C)]
-        struct InputDataInternal {
-            process_priority: c_int,
-            process_memsize: c_int,
-            run_info: [[c_int; 3]; 10],
-            process_name: [c_char; 8],
-        }
-        // the size of the C struct InputDataInternal in bytes _should_ be 136.
-        assert_eq!(136, mem::size_of::<InputDataInternal>());
-
-        let mut data: [u8; 136] = [0; 136];
-        file.read_exact(&mut data)?;
-        let inp: InputDataInternal = unsafe { mem::transmute(data) };
+    /** Memory the process needs in bytes. process_memsize is in KB
+    @returns
+    None if the size doesn't fit in an i32 */
+    pub fn memsize_bytes(&self) -> Option<i32> {
+        self.process_memsize.checked_mul(1000)
+    }
+    /** A process that only asks for memory: no run info, and a placeholder name */
+    #[cfg(test)]
+    pub fn with_memsize(memsize: i32) -> Self {
+        Self {
+            process_priority: 0,
+            process_memsize: memsize,
+            run_info: Vec::new(),
+            process_name: String::from("TEST"),
+        }
+    }
+
+    /** Reads every record in the input queue file, up to MAX_SIZE of them */
+    pub fn read_from_file(filename: &str) -> Result<Vec<ProcessData>, RecordError> {
+        ProcessData::read_up_to(filename, MAX_SIZE)
+    }
+    /** Reads every record in an input queue file: extended records if the file starts with
+    EXTENDED_MAGIC, otherwise 136 byte legacy records until the end of the file
+    @returns
+    Ok(records) in file order
+    Err(RecordError::Truncated) if the file ends partway through a record
+    Err(RecordError::TooManyRecords) if there are more than max_entries records
+    Err(RecordError::InvalidField) if a record has a bad device type
+    Err(RecordError::Header) if an extended file has a version or layout we can't read */
+    pub fn read_up_to(filename: &str, max_entries: usize) -> Result<Vec<ProcessData>, RecordError> {
+        let bytes = fs::read(filename)?;
+        if bytes.starts_with(EXTENDED_MAGIC) {
+            return ProcessData::read_extended(&bytes, max_entries);
+        }
+        let records = split_records(&bytes, INPUT_RECORD_SIZE)?;
+        if records.len() > max_entries {
+            return Err(RecordError::TooManyRecords {
+                count: records.len(),
+                max: max_entries,
+            });
+        }
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(idx, record)| ProcessData::read_one_entry(record, idx))
+            .collect()
+    }
+    /** Writes input queue records to a file, replacing its contents */
+    pub fn write_to_file(filename: &str, records: &[ProcessData]) -> Result<(), RecordError> {
+        let mut bytes = Vec::with_capacity(records.len() * INPUT_RECORD_SIZE);
+        for (idx, record) in records.iter().enumerate() {
+            bytes.extend(record.write_one_entry(idx)?);
+        }
+        File::create(filename)?.write_all(&bytes)?;
+        Ok(())
+    }
+    /** Encodes one input_data_record, the inverse of read_one_entry.
+    Unused run_info rows are written as zeros */
+    fn write_one_entry(&self, idx: usize) -> Result<Vec<u8>, RecordError> {
+        let mut fields = RecordEncoder::new(INPUT_RECORD_SIZE, idx);
+        if self.run_info.len() > RUN_INFO_ROWS {
+            // longer traces need the extended format
+            return Err(fields.invalid(
+                &format!(
+                    "run_info rows (at most {}, or use extended records)",
+                    RUN_INFO_ROWS
+                ),
+                self.run_info.len(),
+            ));
+        }
+        fields.int(self.process_priority);
+        fields.int(self.process_memsize);
+        for info in &self.run_info {
+            fields.int(info.CPU_units);
+            fields.int(info.IO_units);
+            fields.int(info.IO_device_type.code());
+        }
+        for _ in self.run_info.len()..RUN_INFO_ROWS {
+            fields.int(0);
+            fields.int(0);
+            fields.int(0);
+        }
+        fields.name("process_name", &self.process_name, 0)?;
+        Ok(fields.bytes)
+    }
+    /** Reads an extended input file:
+        header: magic "OSIX", version, record count, bytes per run info row
+        each record: priority, memsize, 8 char process name, run info row count, run info rows
+        each run info row: CPU units, IO units, device type, then any fields added by
+            later versions, which are skipped
+    Every field is a 4 byte little-endian int, apart from the magic and name. Unlike legacy
+    records, run info rows are never dropped, so a burst of 0 CPU units is kept */
+    fn read_extended(bytes: &[u8], max_entries: usize) -> Result<Vec<ProcessData>, RecordError> {
+        let mut header = RecordDecoder::new(bytes_at(bytes, 0, EXTENDED_HEADER_SIZE)?, 0);
+        header.pos = EXTENDED_MAGIC.len();
+        let version = header.int();
+        let count = header.int();
+        let burst_size = header.int();
+        if version < 1 || version > EXTENDED_VERSION {
+            return Err(RecordError::Header(format!(
+                "version {} is not supported; this simulator reads up to version {}",
+                version, EXTENDED_VERSION
+            )));
+        }
+        if count < 0 || burst_size < EXTENDED_BURST_SIZE as i32 {
+            return Err(RecordError::Header(format!(
+                "{} records of {} byte run info rows",
+                count, burst_size
+            )));
+        }
+        let (count, burst_size) = (count as usize, burst_size as usize);
+        if count > max_entries {
+            return Err(RecordError::TooManyRecords {
+                count,
+                max: max_entries,
+            });
+        }
+
+        let mut records = Vec::with_capacity(count);
+        let mut offset = EXTENDED_HEADER_SIZE;
+        for idx in 0..count {
+            let mut fields =
+                RecordDecoder::new(bytes_at(bytes, offset, EXTENDED_RECORD_SIZE)?, idx);
+            let process_priority = fields.int();
+            let process_memsize = fields.int();
+            let process_name = fields.name();
+            let rows = fields.int();
+            if rows < 0 {
+                return Err(fields.invalid("run_info rows", rows));
+            }
+            offset += EXTENDED_RECORD_SIZE;
+
+            // the row count comes from the file, so check the rows are all there before
+            // making room for them
+            let rows_size = (rows as usize).saturating_mul(burst_size);
+            let rows_bytes = bytes_at(bytes, offset, rows_size)?;
+            let mut run_info = Vec::with_capacity(rows as usize);
+            for (row, burst) in rows_bytes.chunks_exact(burst_size).enumerate() {
+                let mut burst = RecordDecoder::new(burst, idx);
+                let info = [burst.int(), burst.int(), burst.int()];
+                run_info.push(burst.run_info(row, info)?);
+            }
+            offset += rows_size;
+            records.push(ProcessData {
+                process_priority,
+                process_memsize,
+                run_info,
+                process_name,
+            });
+        }
+        if offset < bytes.len() {
+            return Err(RecordError::Header(format!(
+                "{} bytes after the last of its {} records",
+                bytes.len() - offset,
+                count
+            )));
+        }
+        Ok(records)
+    }
+    /** Writes input queue records to an extended input file, replacing its contents.
+    Any number of run info rows fit in an extended record */
+    pub fn write_extended_to_file(
+        filename: &str,
+        records: &[ProcessData],
+    ) -> Result<(), RecordError> {
+        let mut fields = RecordEncoder::new(EXTENDED_HEADER_SIZE, 0);
+        fields.bytes.extend_from_slice(EXTENDED_MAGIC);
+        fields.int(EXTENDED_VERSION);
+        fields.int(records.len() as i32);
+        fields.int(EXTENDED_BURST_SIZE as i32);
+        for (idx, record) in records.iter().enumerate() {
+            fields.record = idx;
+            fields.int(record.process_priority);
+            fields.int(record.process_memsize);
+            fields.name("process_name", &record.process_name, 0)?;
+            fields.int(record.run_info.len() as i32);
+            for info in &record.run_info {
+                fields.int(info.CPU_units);
+                fields.int(info.IO_units);
+                fields.int(info.IO_device_type.code());
+            }
+        }
+        File::create(filename)?.write_all(&fields.bytes)?;
+        Ok(())
+    }
+    /** Decodes one input_data_record: priority, memsize, 10 run_info rows of
+    (CPU units, IO units, device type), then the 8 char process name */
+    fn read_one_entry(record: &[u8], idx: usize) -> Result<ProcessData, RecordError> {
+        let mut fields = RecordDecoder::new(record, idx);
+        let process_priority = fields.int();
+        let process_memsize = fields.int();
+        let mut run_info = Vec::with_capacity(RUN_INFO_ROWS);
+        for row in 0..RUN_INFO_ROWS {
+            let info = [fields.int(), fields.int(), fields.int()];
+            // only keep run info rows that exist; skip them if every field is empty.
+            // a legacy record can't tell these from a burst of 0 CPU units and no IO
+            if info.iter().all(|x| *x == 0) {
+                continue;
+            }
+            run_info.push(fields.run_info(row, info)?);
+        }
+        let process_name = fields.name();
+
         Ok(ProcessData {
-            process_priority: inp.process_priority,
-            process_memsize: inp.process_memsize,
-            process_name: convert_bytes(&inp.process_name),
-            // use cute array comprehension crate to build up RunInfo vec
-            run_info: c![
-                RunInfo {
-                    CPU_units: info[0],
-                    IO_units: info[1],
-                    IO_device_type: match info[2] {
-                    1 => IODeviceType::Disk,
-                    2 => IODeviceType::Tape,
-                    3 => IODeviceType::CD,
-                    _ => IODeviceType::Unknown
-                }
-            }, for info in inp.run_info.iter()
-                // filter run info for only entries that exist; don't insert if all fields are empty
-                .filter(|info| !info.iter().all(|x| *x == 0))
-            ],
+            process_priority,
+            process_memsize,
+            run_info,
+            process_name,
         })
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    /** Path of a file in res/, the sample workload from the reference simulator */
+    pub fn resource(name: &str) -> String {
+        format!("{}/res/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    /** Path of a scratch file for a test; tests running at the same time need different names */
+    pub fn temp_file(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("os-project-{}-{}", process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /** Checks a record decodes to an InvalidField error for the given field and value */
+    fn assert_invalid<T: fmt::Debug>(
+        result: Result<T, RecordError>,
+        expected_field: &str,
+        expected_value: &str,
+    ) {
+        match result {
+            Err(RecordError::InvalidField {
+                record,
+                field,
+                value,
+            }) => assert_eq!(
+                (record, field.as_str(), value.as_str()),
+                (1, expected_field, expected_value)
+            ),
+            other => panic!("expected {} to be invalid, got {:?}", expected_field, other),
+        }
+    }
+
+    /** The second record of a file in res/, for decoding with one field changed */
+    fn second_record(name: &str, size: usize) -> Vec<u8> {
+        fs::read(resource(name)).unwrap()[size..2 * size].to_vec()
+    }
+
+    #[test]
+    fn unknown_mem_model_code_is_invalid() {
+        let mut record = second_record("OS_OSP.DAT", PARAMS_RECORD_SIZE);
+        record[..4].copy_from_slice(&9i32.to_le_bytes());
+        assert_invalid(OSParams::read_one_entry(&record, 1), "mem_model", "9");
+    }
+
+    #[test]
+    fn unknown_algorithm_name_is_invalid() {
+        let mut record = second_record("OS_OSP.DAT", PARAMS_RECORD_SIZE);
+        record[44..].copy_from_slice(b"SJF    \0");
+        assert_invalid(
+            OSParams::read_one_entry(&record, 1),
+            "pro_algorithm",
+            "\"SJF    \"",
+        );
+    }
+
+    #[test]
+    fn io_on_an_unknown_device_code_is_invalid() {
+        // the first run info row is at byte 8: CPU units, IO units, device type
+        let mut record = second_record("OS_INP.DAT", INPUT_RECORD_SIZE);
+        record[12..16].copy_from_slice(&20i32.to_le_bytes());
+        for code in [7, 0] {
+            record[16..20].copy_from_slice(&i32::to_le_bytes(code));
+            let result = ProcessData::read_one_entry(&record, 1);
+            assert_invalid(result, "run_info[0][2]", &code.to_string());
+        }
+        // a device type is only needed for IO
+        record[12..16].copy_from_slice(&0i32.to_le_bytes());
+        assert!(ProcessData::read_one_entry(&record, 1).is_ok());
+    }
+
+    #[test]
+    fn legacy_file_ending_partway_through_a_record_is_truncated() {
+        let filename = temp_file("truncated.DAT");
+        let mut bytes = fs::read(resource("OS_INP.DAT")).unwrap();
+        bytes.truncate(2 * INPUT_RECORD_SIZE + 10);
+        fs::write(&filename, bytes).unwrap();
+        let result = ProcessData::read_from_file(&filename);
+        fs::remove_file(&filename).unwrap();
+        match result {
+            Err(RecordError::Truncated {
+                offset,
+                len,
+                expected,
+            }) => assert_eq!((offset, len, expected), (272, 10, INPUT_RECORD_SIZE)),
+            other => panic!("expected a truncated record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn extended_row_count_past_the_end_of_the_file_is_truncated() {
+        // header, then one record claiming 0x7fffffff run info rows and holding part of one
+        let mut fields = RecordEncoder::new(EXTENDED_HEADER_SIZE + EXTENDED_RECORD_SIZE, 0);
+        fields.bytes.extend_from_slice(EXTENDED_MAGIC);
+        fields.int(EXTENDED_VERSION);
+        fields.int(1);
+        fields.int(EXTENDED_BURST_SIZE as i32);
+        fields.int(1);
+        fields.int(40);
+        fields.name("process_name", "HUGE", 0).unwrap();
+        fields.int(i32::MAX);
+        fields.int(10);
+        fields.int(0);
+        assert_eq!(fields.bytes.len(), 44);
+
+        match ProcessData::read_extended(&fields.bytes, MAX_SIZE) {
+            Err(RecordError::Truncated {
+                offset,
+                len,
+                expected,
+            }) => {
+                assert_eq!((offset, len), (36, 8));
+                assert_eq!(expected, i32::MAX as usize * EXTENDED_BURST_SIZE);
+            }
+            other => panic!("expected a truncated record, got {:?}", other),
+        }
+    }
+
+    /** A process with 13 bursts, more than a legacy record holds, including bursts of 0 CPU
+    units that a legacy record would drop */
+    pub fn long_trace() -> ProcessData {
+        let run_info = (0..13)
+            .map(|row| RunInfo {
+                CPU_units: if row % 4 == 0 { 0 } else { row },
+                IO_units: if row < 12 { 5 } else { 0 },
+                IO_device_type: match row % 3 {
+                    _ if row == 12 => IODeviceType::Unknown,
+                    0 => IODeviceType::Disk,
+                    1 => IODeviceType::Tape,
+                    _ => IODeviceType::CD,
+                },
+            })
+            .collect();
+        ProcessData {
+            run_info,
+            ..ProcessData::with_memsize(40)
+        }
+    }
+
+    fn bursts(record: &ProcessData) -> Vec<(i32, i32, i32)> {
+        record
+            .run_info
+            .iter()
+            .map(|info| (info.CPU_units, info.IO_units, info.IO_device_type.code()))
+            .collect()
+    }
+
+    #[test]
+    fn extended_records_keep_long_traces_and_zero_cpu_bursts() {
+        let filename = temp_file("long.xdat");
+        let records = vec![long_trace(), ProcessData::with_memsize(10)];
+        ProcessData::write_extended_to_file(&filename, &records).unwrap();
+        let bytes = fs::read(&filename).unwrap();
+        let read = ProcessData::read_from_file(&filename).unwrap();
+        ProcessData::write_extended_to_file(&filename, &read).unwrap();
+        let written = fs::read(&filename).unwrap();
+        fs::remove_file(&filename).unwrap();
+
+        assert_eq!(read.len(), 2);
+        assert_eq!(bursts(&read[0]), bursts(&records[0]));
+        assert_eq!(read[0].run_info.len(), 13);
+        assert_eq!(read[0].run_info[0].CPU_units, 0);
+        assert!(read[1].run_info.is_empty());
+        assert_eq!(written, bytes);
+    }
+
+    #[test]
+    fn extended_rows_must_all_be_in_the_file() {
+        let filename = temp_file("rows.xdat");
+        ProcessData::write_extended_to_file(&filename, &[long_trace()]).unwrap();
+        let bytes = fs::read(&filename).unwrap();
+        fs::remove_file(&filename).unwrap();
+        let rows_at = EXTENDED_HEADER_SIZE + EXTENDED_RECORD_SIZE;
+        assert_eq!(bytes.len(), rows_at + 13 * EXTENDED_BURST_SIZE);
+
+        // one row short
+        let short = &bytes[..bytes.len() - EXTENDED_BURST_SIZE];
+        match ProcessData::read_extended(short, MAX_SIZE) {
+            Err(RecordError::Truncated {
+                offset,
+                len,
+                expected,
+            }) => assert_eq!(
+                (offset, len, expected),
+                (rows_at, 12 * EXTENDED_BURST_SIZE, 13 * EXTENDED_BURST_SIZE)
+            ),
+            other => panic!("expected a truncated record, got {:?}", other),
+        }
+        // a row too many
+        let mut long = bytes.to_vec();
+        long.extend_from_slice(&[0; EXTENDED_BURST_SIZE]);
+        assert!(matches!(
+            ProcessData::read_extended(&long, MAX_SIZE),
+            Err(RecordError::Header(_))
+        ));
+    }
+
+    #[test]
+    fn extended_rows_from_later_versions_skip_the_fields_they_add() {
+        // 16 byte rows: CPU units, IO units, device type, then a field this version ignores
+        let mut fields = RecordEncoder::new(EXTENDED_HEADER_SIZE, 0);
+        fields.bytes.extend_from_slice(EXTENDED_MAGIC);
+        fields.int(EXTENDED_VERSION);
+        fields.int(1);
+        fields.int(16);
+        fields.int(2);
+        fields.int(40);
+        fields.name("process_name", "WIDE", 0).unwrap();
+        fields.int(2);
+        for row in [[10, 20, 1, 99], [0, 0, 0, 99]] {
+            for field in row {
+                fields.int(field);
+            }
+        }
+        let read = ProcessData::read_extended(&fields.bytes, MAX_SIZE).unwrap();
+        assert_eq!(read[0].process_name, "WIDE");
+        assert_eq!(bursts(&read[0]), vec![(10, 20, 1), (0, 0, 0)]);
+    }
+
+    #[test]
+    fn params_are_written_back_byte_for_byte() {
+        let original = resource("OS_OSP.DAT");
+        let copy = temp_file("OS_OSP.DAT");
+        let params = OSParams::read_all_from_file(&original).unwrap();
+        OSParams::write_to_file(&copy, &params).unwrap();
+        let written = fs::read(&copy).unwrap();
+        fs::remove_file(&copy).unwrap();
+        assert_eq!(written, fs::read(&original).unwrap());
+    }
+
+    #[test]
+    fn input_queue_is_written_back_byte_for_byte() {
+        let original = resource("OS_INP.DAT");
+        let copy = temp_file("OS_INP.DAT");
+        let records = ProcessData::read_from_file(&original).unwrap();
+        ProcessData::write_to_file(&copy, &records).unwrap();
+        let written = fs::read(&copy).unwrap();
+        fs::remove_file(&copy).unwrap();
+        assert_eq!(written, fs::read(&original).unwrap());
+    }
+}
@@ -0,0 +1,524 @@
+use crate::records::{Algorithm, IODeviceType, MemModel, OSParams, ProcessData};
+use std::fmt;
+
+/** How bad a problem with the loaded data is */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    Warning, // the simulation can run, but probably not the way it was meant to
+    Error,   // the simulation can't run, or would crash or never finish
+}
+
+/** A problem found in one field of a loaded record */
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub severity: Severity,
+    pub field: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn error(field: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            field: field.to_string(),
+            message,
+        }
+    }
+    fn warning(field: &str, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}: {}", severity, self.field, self.message)
+    }
+}
+
+/** Checks if any problem stops the simulation */
+pub fn has_errors(problems: &[Problem]) -> bool {
+    problems
+        .iter()
+        .any(|problem| problem.severity == Severity::Error)
+}
+
+impl OSParams {
+    /** Checks the parameters can be simulated
+    @returns
+    every problem found, in field order. Empty if the parameters are fine */
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        match self.mem_model {
+            MemModel::Fixed | MemModel::Paged => {
+                // process memory sizes are in KB, so a block must hold at least 1 KB
+                if self.mem_fix_block_size < 1000 {
+                    problems.push(Problem::error(
+                        "mem_fix_block_size",
+                        format!(
+                            "{} bytes is less than the smallest block size of 1000",
+                            self.mem_fix_block_size
+                        ),
+                    ));
+                } else if self.mem_fix_block_size % 1000 != 0 {
+                    problems.push(Problem::warning(
+                        "mem_fix_block_size",
+                        format!(
+                            "{} bytes is not a whole number of KB; it is rounded down to {}",
+                            self.mem_fix_block_size,
+                            self.mem_fix_block_size / 1000 * 1000
+                        ),
+                    ));
+                }
+                if self.mem_fix_total_blocks < 1 {
+                    problems.push(Problem::error(
+                        "mem_fix_total_blocks",
+                        format!("{} blocks leaves no memory", self.mem_fix_total_blocks),
+                    ));
+                }
+            }
+            MemModel::Variable => {
+                if self.mem_var_totsize < 1 {
+                    problems.push(Problem::error(
+                        "mem_var_totsize",
+                        format!("{} bytes leaves no memory", self.mem_var_totsize),
+                    ));
+                }
+                if self.mem_var_maxsize < 0 {
+                    problems.push(Problem::error(
+                        "mem_var_maxsize",
+                        format!("{} bytes is negative", self.mem_var_maxsize),
+                    ));
+                } else if self.mem_var_maxsize > self.mem_var_totsize {
+                    problems.push(Problem::warning(
+                        "mem_var_maxsize",
+                        format!(
+                            "{} bytes is more than the {} bytes of memory",
+                            self.mem_var_maxsize, self.mem_var_totsize
+                        ),
+                    ));
+                }
+            }
+            MemModel::None => {}
+            MemModel::Unknown => problems.push(Problem::error(
+                "mem_model",
+                "unknown memory model".to_string(),
+            )),
+        }
+        if self.pro_algorithm == Algorithm::Unknown {
+            problems.push(Problem::error(
+                "pro_algorithm",
+                "unknown process management algorithm".to_string(),
+            ));
+        }
+        if self.pro_max_tasks < 1 {
+            problems.push(Problem::error(
+                "pro_max_tasks",
+                format!("{} tasks means no process can ever run", self.pro_max_tasks),
+            ));
+        }
+        if self.init_quantum < 0 {
+            problems.push(Problem::error(
+                "init_quantum",
+                format!("{} is negative", self.init_quantum),
+            ));
+        } else if self.init_quantum == 0 {
+            problems.push(Problem::warning(
+                "init_quantum",
+                "0 turns off time slicing; processes run until they block".to_string(),
+            ));
+        }
+        for (field, units) in [
+            ("disk_units", self.disk_units),
+            ("tape_units", self.tape_units),
+            ("cdrom_units", self.cdrom_units),
+        ] {
+            if units < 0 {
+                problems.push(Problem::error(field, format!("{} is negative", units)));
+            } else if units == 0 {
+                problems.push(Problem::warning(
+                    field,
+                    "0 units; any IO on this device waits forever".to_string(),
+                ));
+            }
+        }
+        if self.every_n_units < 0 {
+            problems.push(Problem::error(
+                "every_n_units",
+                format!("{} is negative", self.every_n_units),
+            ));
+        } else if self.every_n_units == 0 {
+            problems.push(Problem::warning(
+                "every_n_units",
+                "0 turns off the detail printed every N units".to_string(),
+            ));
+        }
+        problems
+    }
+
+    /** Checks the parameters can run every process in the workload: a process that does IO on
+    a device type with 0 units blocks forever
+    @returns
+    every problem found, in input order. Empty if the workload fits the parameters */
+    pub fn validate_workload(&self, records: &[ProcessData]) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        for (idx, record) in records.iter().enumerate() {
+            for (field, device, units) in [
+                ("disk_units", IODeviceType::Disk, self.disk_units),
+                ("tape_units", IODeviceType::Tape, self.tape_units),
+                ("cdrom_units", IODeviceType::CD, self.cdrom_units),
+            ] {
+                let row = record
+                    .run_info
+                    .iter()
+                    .position(|info| info.IO_units > 0 && info.IO_device_type == device);
+                match row {
+                    Some(row) if units == 0 => problems.push(Problem::error(
+                        field,
+                        format!(
+                            "{} units, but {} (Input Data List # {}) does {:?} IO in run_info[{}]",
+                            units,
+                            record.process_name,
+                            idx + 1,
+                            device,
+                            row
+                        ),
+                    )),
+                    _ => {}
+                }
+            }
+        }
+        problems
+    }
+}
+
+impl ProcessData {
+    /** Checks the process can be simulated
+    @returns
+    every problem found, in field order. Empty if the process is fine */
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        if self.process_name.trim().is_empty() {
+            problems.push(Problem::error(
+                "process_name",
+                "the name is empty".to_string(),
+            ));
+        } else if self.process_name.len() > 7 {
+            problems.push(Problem::warning(
+                "process_name",
+                format!(
+                    "'{}' is longer than the 7 characters a .DAT file holds",
+                    self.process_name
+                ),
+            ));
+        }
+        if self.process_memsize < 0 {
+            problems.push(Problem::error(
+                "process_memsize",
+                format!("{} KB is negative", self.process_memsize),
+            ));
+        } else if self.memsize_bytes().is_none() {
+            problems.push(Problem::error(
+                "process_memsize",
+                format!(
+                    "{} KB is more than the largest size of {} KB",
+                    self.process_memsize,
+                    i32::MAX / 1000
+                ),
+            ));
+        }
+        if self.run_info.is_empty() {
+            problems.push(Problem::warning(
+                "run_info",
+                "no CPU bursts; the process finishes as soon as it starts".to_string(),
+            ));
+        }
+        for (row, info) in self.run_info.iter().enumerate() {
+            let field = |column| format!("run_info[{}][{}]", row, column);
+            if info.CPU_units < 0 {
+                problems.push(Problem::error(
+                    &field(0),
+                    format!("{} CPU units is negative", info.CPU_units),
+                ));
+            }
+            if info.IO_units < 0 {
+                problems.push(Problem::error(
+                    &field(1),
+                    format!("{} IO units is negative", info.IO_units),
+                ));
+            }
+            match info.IO_device_type {
+                IODeviceType::Unknown if info.IO_units > 0 => problems.push(Problem::error(
+                    &field(2),
+                    format!("{} IO units with no device type", info.IO_units),
+                )),
+                IODeviceType::Unknown => {}
+                device if info.IO_units == 0 => problems.push(Problem::warning(
+                    &field(2),
+                    format!("{:?} device with no IO units", device),
+                )),
+                _ => {}
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::RunInfo;
+
+    /** The severity and field of each problem, in order */
+    fn found(problems: Vec<Problem>) -> Vec<(Severity, String)> {
+        problems
+            .into_iter()
+            .map(|problem| (problem.severity, problem.field))
+            .collect()
+    }
+
+    fn error(field: &str) -> Vec<(Severity, String)> {
+        vec![(Severity::Error, field.to_string())]
+    }
+
+    fn warning(field: &str) -> Vec<(Severity, String)> {
+        vec![(Severity::Warning, field.to_string())]
+    }
+
+    fn params() -> OSParams {
+        OSParams::with_fixed_blocks(1000, 10)
+    }
+
+    fn variable(maxsize: i32, totsize: i32) -> OSParams {
+        OSParams {
+            mem_model: MemModel::Variable,
+            mem_var_maxsize: maxsize,
+            mem_var_totsize: totsize,
+            ..params()
+        }
+    }
+
+    /** A process with disk IO, then a last burst with none */
+    fn process() -> ProcessData {
+        ProcessData {
+            run_info: vec![
+                RunInfo {
+                    CPU_units: 10,
+                    IO_units: 20,
+                    IO_device_type: IODeviceType::Disk,
+                },
+                RunInfo {
+                    CPU_units: 5,
+                    IO_units: 0,
+                    IO_device_type: IODeviceType::Unknown,
+                },
+            ],
+            ..ProcessData::with_memsize(10)
+        }
+    }
+
+    #[test]
+    fn sound_params_and_processes_have_no_problems() {
+        assert!(params().validate().is_empty());
+        assert!(variable(20000, 100000).validate().is_empty());
+        assert!(process().validate().is_empty());
+        assert!(params().validate_workload(&[process()]).is_empty());
+    }
+
+    #[test]
+    fn params_problems() {
+        let cases = [
+            (
+                OSParams {
+                    mem_fix_block_size: 999,
+                    ..params()
+                },
+                error("mem_fix_block_size"),
+            ),
+            (
+                OSParams {
+                    mem_fix_block_size: 1500,
+                    ..params()
+                },
+                warning("mem_fix_block_size"),
+            ),
+            (
+                OSParams {
+                    mem_model: MemModel::Paged,
+                    mem_fix_total_blocks: 0,
+                    ..params()
+                },
+                error("mem_fix_total_blocks"),
+            ),
+            (variable(0, 0), error("mem_var_totsize")),
+            (variable(-1, 100000), error("mem_var_maxsize")),
+            (variable(200000, 100000), warning("mem_var_maxsize")),
+            (
+                OSParams {
+                    mem_model: MemModel::Unknown,
+                    ..params()
+                },
+                error("mem_model"),
+            ),
+            (
+                OSParams {
+                    pro_algorithm: Algorithm::Unknown,
+                    ..params()
+                },
+                error("pro_algorithm"),
+            ),
+            (
+                OSParams {
+                    pro_max_tasks: 0,
+                    ..params()
+                },
+                error("pro_max_tasks"),
+            ),
+            (
+                OSParams {
+                    init_quantum: -1,
+                    ..params()
+                },
+                error("init_quantum"),
+            ),
+            (
+                OSParams {
+                    init_quantum: 0,
+                    ..params()
+                },
+                warning("init_quantum"),
+            ),
+            (
+                OSParams {
+                    disk_units: -1,
+                    ..params()
+                },
+                error("disk_units"),
+            ),
+            (
+                OSParams {
+                    tape_units: 0,
+                    ..params()
+                },
+                warning("tape_units"),
+            ),
+            (
+                OSParams {
+                    every_n_units: -5,
+                    ..params()
+                },
+                error("every_n_units"),
+            ),
+            (
+                OSParams {
+                    every_n_units: 0,
+                    ..params()
+                },
+                warning("every_n_units"),
+            ),
+        ];
+        for (param, expected) in cases {
+            assert_eq!(found(param.validate()), expected);
+        }
+    }
+
+    #[test]
+    fn process_problems() {
+        let with_burst = |CPU_units, IO_units, IO_device_type| {
+            let mut process = process();
+            process.run_info[1] = RunInfo {
+                CPU_units,
+                IO_units,
+                IO_device_type,
+            };
+            process
+        };
+        let cases = [
+            (
+                ProcessData {
+                    process_name: " ".to_string(),
+                    ..process()
+                },
+                error("process_name"),
+            ),
+            (
+                ProcessData {
+                    process_name: "TOOLONGX".to_string(),
+                    ..process()
+                },
+                warning("process_name"),
+            ),
+            (
+                ProcessData {
+                    process_memsize: -1,
+                    ..process()
+                },
+                error("process_memsize"),
+            ),
+            (
+                ProcessData {
+                    process_memsize: i32::MAX / 1000 + 1,
+                    ..process()
+                },
+                error("process_memsize"),
+            ),
+            (ProcessData::with_memsize(10), warning("run_info")),
+            (
+                with_burst(-1, 0, IODeviceType::Unknown),
+                error("run_info[1][0]"),
+            ),
+            (
+                with_burst(5, -1, IODeviceType::Unknown),
+                error("run_info[1][1]"),
+            ),
+            (
+                with_burst(5, 10, IODeviceType::Unknown),
+                error("run_info[1][2]"),
+            ),
+            (
+                with_burst(5, 0, IODeviceType::CD),
+                warning("run_info[1][2]"),
+            ),
+        ];
+        for (process, expected) in cases {
+            assert_eq!(found(process.validate()), expected);
+        }
+        // the largest size that converts to bytes is fine
+        let largest = ProcessData {
+            process_memsize: i32::MAX / 1000,
+            ..process()
+        };
+        assert!(largest.validate().is_empty());
+    }
+
+    #[test]
+    fn workload_io_needs_a_unit_of_its_device() {
+        let no_disks = OSParams {
+            disk_units: 0,
+            ..params()
+        };
+        let workload = [ProcessData::with_memsize(10), process(), process()];
+        let problems = no_disks.validate_workload(&workload);
+        assert_eq!(
+            found(problems.clone()),
+            [error("disk_units"), error("disk_units")].concat()
+        );
+        assert_eq!(
+            problems[0].message,
+            "0 units, but TEST (Input Data List # 2) does Disk IO in run_info[0]"
+        );
+        // no process uses tapes, so having none is fine
+        let no_tapes = OSParams {
+            tape_units: 0,
+            ..params()
+        };
+        assert!(no_tapes.validate_workload(&workload).is_empty());
+    }
+}
@@ -0,0 +1,630 @@
+use crate::records::{
+    IODeviceType, MemModel, OSParams, ProcessData, RecordError, RunInfo, MAX_SIZE,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/** File formats a workload can be kept in, chosen by file extension */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    Binary,   // the .DAT record layout from OS_DS.H
+    Extended, // .xdat: extended input records with any number of run info rows
+    Text,     // .txt: the text tables from OS_DOC.TXT
+    Toml,     // .toml
+    Yaml,     // .yaml or .yml
+}
+
+impl Format {
+    /** Picks the format from a file's extension. Anything unrecognised is binary.
+    Binary input files are read as extended records if they start with the extended magic */
+    pub fn of(filename: &str) -> Format {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        match extension.as_deref() {
+            Some("xdat") => Format::Extended,
+            Some("txt") => Format::Text,
+            Some("toml") => Format::Toml,
+            Some("yaml") | Some("yml") => Format::Yaml,
+            _ => Format::Binary,
+        }
+    }
+}
+
+/** Reads O/S parameter records in the format of the file's extension */
+pub fn read_params(filename: &str) -> Result<Vec<OSParams>, RecordError> {
+    match Format::of(filename) {
+        Format::Binary | Format::Extended => OSParams::read_all_from_file(filename),
+        Format::Text => parse_params_table(&fs::read_to_string(filename)?),
+        Format::Toml => from_toml::<ParamsFile>(&fs::read_to_string(filename)?)?.into_records(),
+        Format::Yaml => from_yaml::<ParamsFile>(&fs::read_to_string(filename)?)?.into_records(),
+    }
+}
+
+/** Reads input queue records in the format of the file's extension
+@returns
+Err(RecordError::TooManyRecords) if there are more than MAX_SIZE records, in any format */
+pub fn read_processes(filename: &str) -> Result<Vec<ProcessData>, RecordError> {
+    let records = match Format::of(filename) {
+        // binary files are checked against MAX_SIZE once the whole file is read
+        Format::Binary | Format::Extended => return ProcessData::read_from_file(filename),
+        Format::Text => parse_input_table(&fs::read_to_string(filename)?)?,
+        Format::Toml => from_toml::<InputFile>(&fs::read_to_string(filename)?)?.into_records()?,
+        Format::Yaml => from_yaml::<InputFile>(&fs::read_to_string(filename)?)?.into_records()?,
+    };
+    if records.len() > MAX_SIZE {
+        return Err(RecordError::TooManyRecords {
+            count: records.len(),
+            max: MAX_SIZE,
+        });
+    }
+    Ok(records)
+}
+
+/** Writes O/S parameter records in the format of the file's extension */
+pub fn write_params(filename: &str, params: &[OSParams]) -> Result<(), RecordError> {
+    let text = match Format::of(filename) {
+        // there is only one binary parameter record layout
+        Format::Binary | Format::Extended => return OSParams::write_to_file(filename, params),
+        Format::Text => format_params_table(params),
+        Format::Toml => to_toml(&ParamsFile::from_records(params))?,
+        Format::Yaml => to_yaml(&ParamsFile::from_records(params))?,
+    };
+    fs::write(filename, text)?;
+    Ok(())
+}
+
+/** Writes input queue records in the format of the file's extension */
+pub fn write_processes(filename: &str, records: &[ProcessData]) -> Result<(), RecordError> {
+    let text = match Format::of(filename) {
+        Format::Binary => return ProcessData::write_to_file(filename, records),
+        Format::Extended => return ProcessData::write_extended_to_file(filename, records),
+        Format::Text => format_input_table(records),
+        Format::Toml => to_toml(&InputFile::from_records(records))?,
+        Format::Yaml => to_yaml(&InputFile::from_records(records))?,
+    };
+    fs::write(filename, text)?;
+    Ok(())
+}
+
+/*** Text tables ***/
+
+/** Table rows are the lines that start with a row number, like " 1) F    5000 ...".
+Titles, column headings and blank lines are skipped
+@returns
+(line number, the row without its row number) for every row */
+fn table_rows(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines().enumerate().filter_map(|(idx, line)| {
+        let (number, rest) = line.trim_start().split_once(')')?;
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some((idx + 1, rest))
+    })
+}
+
+fn syntax_error(line: usize, message: String) -> RecordError {
+    RecordError::Text(format!("line {}: {}", line, message))
+}
+
+fn parse_column(line: usize, column: &str, value: &str) -> Result<i32, RecordError> {
+    value.parse().map_err(|_| {
+        syntax_error(
+            line,
+            format!("{} must be a number, got '{}'", column, value),
+        )
+    })
+}
+
+/** Parses the "Operating System Parameters" table:
+Mem Mode, Blk Size, Num Blks, Max Proc., Pro. Alg., Init Qtm., Disk, Tape and CD units,
+Print every-N-units. For the variable memory model the Blk Size and Num Blks columns hold
+the maximum partition size and total memory size instead; for the paged memory model
+they hold the page size and number of frames */
+pub fn parse_params_table(text: &str) -> Result<Vec<OSParams>, RecordError> {
+    let mut params = Vec::new();
+    for (line, row) in table_rows(text) {
+        let columns: Vec<&str> = row.split_whitespace().collect();
+        if columns.len() != 10 {
+            return Err(syntax_error(
+                line,
+                format!("expected 10 parameter columns, got {}", columns.len()),
+            ));
+        }
+        let mem_model: MemModel = columns[0].parse().map_err(|e| syntax_error(line, e))?;
+        let size = parse_column(line, "Blk Size", columns[1])?;
+        let count = parse_column(line, "Num Blks", columns[2])?;
+        let (fixed, variable) = match mem_model {
+            MemModel::Variable => ((0, 0), (size, count)),
+            _ => ((size, count), (0, 0)),
+        };
+        params.push(OSParams {
+            mem_model,
+            pro_algorithm: columns[4].parse().map_err(|e| syntax_error(line, e))?,
+            mem_fix_block_size: fixed.0,
+            mem_fix_total_blocks: fixed.1,
+            mem_var_maxsize: variable.0,
+            mem_var_totsize: variable.1,
+            pro_max_tasks: parse_column(line, "Max Proc.", columns[3])?,
+            init_quantum: parse_column(line, "Init Qtm.", columns[5])?,
+            disk_units: parse_column(line, "Disk unts", columns[6])?,
+            tape_units: parse_column(line, "Tape unts", columns[7])?,
+            cdrom_units: parse_column(line, "CD unts", columns[8])?,
+            every_n_units: parse_column(line, "Print every-N-unts", columns[9])?,
+        });
+    }
+    Ok(params)
+}
+
+/** Formats O/S parameters as the "Operating System Parameters" table */
+pub fn format_params_table(params: &[OSParams]) -> String {
+    let mut text = String::from(
+        "Operating System Parameters:
+   Mem   Blk   Num   Max   Pro.  Init  Disk Tape CD    Print
+   Mode  Size  Blks  Proc. Alg.  Qtm.  unts unts unts  every-N-unts
+
+",
+    );
+    for (idx, param) in params.iter().enumerate() {
+        let (size, count) = match param.mem_model {
+            MemModel::Variable => (param.mem_var_maxsize, param.mem_var_totsize),
+            _ => (param.mem_fix_block_size, param.mem_fix_total_blocks),
+        };
+        text.push_str(&format!(
+            "{:>2}) {:<5}{:<6}{:<6}{:<6}{:<6}{:<6}{:>2}{:>5}{:>5}{:>8}\n",
+            idx + 1,
+            param.mem_model.letter(),
+            size,
+            count,
+            param.pro_max_tasks,
+            param.pro_algorithm,
+            param.init_quantum,
+            param.disk_units,
+            param.tape_units,
+            param.cdrom_units,
+            param.every_n_units
+        ));
+    }
+    text
+}
+
+/** Parses one CPU burst from the Run-Time Characteristics column:
+"CPU, IO-DEVICE", or just "CPU" for a burst with no IO */
+fn parse_burst(line: usize, burst: &str) -> Result<RunInfo, RecordError> {
+    let (cpu, io) = match burst.split_once(',') {
+        Some((cpu, io)) => (cpu, Some(io)),
+        None => (burst, None),
+    };
+    let CPU_units = parse_column(line, "CPU units", cpu.trim())?;
+    let (IO_units, device) = match io {
+        Some(io) => {
+            let (units, device) = io.split_once('-').ok_or_else(|| {
+                syntax_error(line, format!("expected IO-DEVICE, got '{}'", io.trim()))
+            })?;
+            (
+                parse_column(line, "IO units", units.trim())?,
+                parse_column(line, "IO device", device.trim())?,
+            )
+        }
+        None => (0, 0),
+    };
+    let IO_device_type = IODeviceType::from_code(device)
+        .ok_or_else(|| syntax_error(line, format!("unknown IO device {}", device)))?;
+    Ok(RunInfo {
+        CPU_units,
+        IO_units,
+        IO_device_type,
+    })
+}
+
+/** Parses the "Input Data for Problem" table: Program Name, Memory Needs, Init. Pri.,
+then the Run-Time Characteristics as CPU bursts separated by '|', like
+"10, 20-1| 8, 20-1| 8" */
+pub fn parse_input_table(text: &str) -> Result<Vec<ProcessData>, RecordError> {
+    let mut records = Vec::new();
+    for (line, row) in table_rows(text) {
+        let mut columns = row.split_whitespace();
+        let (name, memsize, priority) = match (columns.next(), columns.next(), columns.next()) {
+            (Some(name), Some(memsize), Some(priority)) => (name, memsize, priority),
+            _ => {
+                return Err(syntax_error(
+                    line,
+                    "expected a name, memory needs and priority".to_string(),
+                ))
+            }
+        };
+        let bursts: String = columns.collect::<Vec<&str>>().join(" ");
+        let run_info = bursts
+            .split('|')
+            .filter(|burst| !burst.trim().is_empty())
+            .map(|burst| parse_burst(line, burst))
+            .collect::<Result<Vec<RunInfo>, RecordError>>()?;
+        records.push(ProcessData {
+            process_priority: parse_column(line, "Init. Pri.", priority)?,
+            process_memsize: parse_column(line, "Memory Needs", memsize)?,
+            run_info,
+            process_name: name.to_string(),
+        });
+    }
+    Ok(records)
+}
+
+/** Formats input queue records as the "Input Data for Problem" table */
+pub fn format_input_table(records: &[ProcessData]) -> String {
+    let mut text = String::from(
+        "Input Data for Problem:
+   Program  Memory  Init. Run-Time Characteristics
+    Name     Needs   Pri.
+
+",
+    );
+    for (idx, record) in records.iter().enumerate() {
+        let bursts: Vec<String> = record
+            .run_info
+            .iter()
+            .map(|info| match info.IO_device_type {
+                IODeviceType::Unknown if info.IO_units == 0 => format!("{:>2}", info.CPU_units),
+                _ => format!(
+                    "{:>2},{:>3}-{}",
+                    info.CPU_units,
+                    info.IO_units,
+                    info.IO_device_type.code()
+                ),
+            })
+            .collect();
+        text.push_str(&format!(
+            "{:>2}) {:<8}{:>4}{:>7}    {}\n",
+            idx + 1,
+            record.process_name,
+            record.process_memsize,
+            record.process_priority,
+            bursts.join("|")
+        ));
+    }
+    text
+}
+
+/*** TOML and YAML schema ***/
+
+/** O/S parameter file: a list of [[params]] tables, one per simulation run */
+#[derive(Serialize, Deserialize)]
+struct ParamsFile {
+    params: Vec<ParamsEntry>,
+}
+
+/** One set of O/S parameters. Field names match dat-tool's */
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParamsEntry {
+    mem_model: String, // None, Fixed, Variable or Paged
+    #[serde(default)]
+    block_size: i32,
+    #[serde(default)]
+    total_blocks: i32,
+    #[serde(default)]
+    var_maxsize: i32,
+    #[serde(default)]
+    var_totsize: i32,
+    max_tasks: i32,
+    algorithm: String, // FIFO, IPRI or MLFQ
+    quantum: i32,
+    disk: i32,
+    tape: i32,
+    cd: i32,
+    #[serde(default)]
+    every_n: i32,
+}
+
+/** Input queue file: a list of [[process]] tables, in input queue order */
+#[derive(Serialize, Deserialize)]
+struct InputFile {
+    process: Vec<ProcessEntry>,
+}
+
+/** One process. Each run_info row is [CPU units, IO units, IO device],
+with device 1=disk 2=tape 3=cd, or 0 for a burst with no IO */
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProcessEntry {
+    name: String,
+    priority: i32,
+    memsize: i32,
+    run_info: Vec<[i32; 3]>,
+}
+
+impl ParamsFile {
+    fn from_records(params: &[OSParams]) -> Self {
+        Self {
+            params: params
+                .iter()
+                .map(|param| ParamsEntry {
+                    mem_model: param.mem_model.to_string(),
+                    block_size: param.mem_fix_block_size,
+                    total_blocks: param.mem_fix_total_blocks,
+                    var_maxsize: param.mem_var_maxsize,
+                    var_totsize: param.mem_var_totsize,
+                    max_tasks: param.pro_max_tasks,
+                    algorithm: param.pro_algorithm.to_string(),
+                    quantum: param.init_quantum,
+                    disk: param.disk_units,
+                    tape: param.tape_units,
+                    cd: param.cdrom_units,
+                    every_n: param.every_n_units,
+                })
+                .collect(),
+        }
+    }
+
+    fn into_records(self) -> Result<Vec<OSParams>, RecordError> {
+        let invalid = |record, field: &str, value: String| RecordError::InvalidField {
+            record,
+            field: field.to_string(),
+            value,
+        };
+        self.params
+            .into_iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                Ok(OSParams {
+                    mem_model: entry
+                        .mem_model
+                        .parse()
+                        .map_err(|_| invalid(idx, "mem_model", entry.mem_model.clone()))?,
+                    pro_algorithm: entry
+                        .algorithm
+                        .parse()
+                        .map_err(|_| invalid(idx, "algorithm", entry.algorithm.clone()))?,
+                    mem_fix_block_size: entry.block_size,
+                    mem_fix_total_blocks: entry.total_blocks,
+                    mem_var_maxsize: entry.var_maxsize,
+                    mem_var_totsize: entry.var_totsize,
+                    pro_max_tasks: entry.max_tasks,
+                    init_quantum: entry.quantum,
+                    disk_units: entry.disk,
+                    tape_units: entry.tape,
+                    cdrom_units: entry.cd,
+                    every_n_units: entry.every_n,
+                })
+            })
+            .collect()
+    }
+}
+
+impl InputFile {
+    fn from_records(records: &[ProcessData]) -> Self {
+        Self {
+            process: records
+                .iter()
+                .map(|record| ProcessEntry {
+                    name: record.process_name.clone(),
+                    priority: record.process_priority,
+                    memsize: record.process_memsize,
+                    run_info: record
+                        .run_info
+                        .iter()
+                        .map(|info| [info.CPU_units, info.IO_units, info.IO_device_type.code()])
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn into_records(self) -> Result<Vec<ProcessData>, RecordError> {
+        self.process
+            .into_iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let run_info = entry
+                    .run_info
+                    .iter()
+                    .enumerate()
+                    .map(|(row, info)| {
+                        let IO_device_type = IODeviceType::from_code(info[2]).ok_or_else(|| {
+                            RecordError::InvalidField {
+                                record: idx,
+                                field: format!("run_info[{}][2]", row),
+                                value: info[2].to_string(),
+                            }
+                        })?;
+                        Ok(RunInfo {
+                            CPU_units: info[0],
+                            IO_units: info[1],
+                            IO_device_type,
+                        })
+                    })
+                    .collect::<Result<Vec<RunInfo>, RecordError>>()?;
+                Ok(ProcessData {
+                    process_priority: entry.priority,
+                    process_memsize: entry.memsize,
+                    run_info,
+                    process_name: entry.name,
+                })
+            })
+            .collect()
+    }
+}
+
+fn from_toml<T: for<'de> Deserialize<'de>>(text: &str) -> Result<T, RecordError> {
+    toml::from_str(text).map_err(|e| RecordError::Text(e.to_string()))
+}
+
+fn to_toml<T: Serialize>(value: &T) -> Result<String, RecordError> {
+    toml::to_string(value).map_err(|e| RecordError::Text(e.to_string()))
+}
+
+fn from_yaml<T: for<'de> Deserialize<'de>>(text: &str) -> Result<T, RecordError> {
+    serde_yaml::from_str(text).map_err(|e| RecordError::Text(e.to_string()))
+}
+
+fn to_yaml<T: Serialize>(value: &T) -> Result<String, RecordError> {
+    serde_yaml::to_string(value).map_err(|e| RecordError::Text(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::tests::{long_trace, resource, temp_file};
+
+    /** Converts a .DAT file in res/ to `extension` and back, checking the bytes are unchanged */
+    fn assert_round_trip<R>(
+        name: &str,
+        extension: &str,
+        read: fn(&str) -> Result<Vec<R>, RecordError>,
+        write: fn(&str, &[R]) -> Result<(), RecordError>,
+    ) {
+        let original = resource(name);
+        let converted = temp_file(&format!("{}.{}", name, extension));
+        let copy = temp_file(&format!("{}.{}.DAT", name, extension));
+        write(&converted, &read(&original).unwrap()).unwrap();
+        write(&copy, &read(&converted).unwrap()).unwrap();
+        let bytes = fs::read(&copy).unwrap();
+        fs::remove_file(&converted).unwrap();
+        fs::remove_file(&copy).unwrap();
+        assert!(
+            bytes == fs::read(&original).unwrap(),
+            "{} changed going through .{}",
+            name,
+            extension
+        );
+    }
+
+    #[test]
+    fn params_round_trip_through_every_format() {
+        for extension in ["txt", "toml", "yaml"] {
+            assert_round_trip("OS_OSP.DAT", extension, read_params, write_params);
+        }
+    }
+
+    #[test]
+    fn input_queue_round_trips_through_every_format() {
+        for extension in ["txt", "toml", "yaml", "xdat"] {
+            assert_round_trip("OS_INP.DAT", extension, read_processes, write_processes);
+        }
+    }
+
+    #[test]
+    fn long_traces_round_trip_through_every_text_format() {
+        let original = temp_file("long-trace.xdat");
+        ProcessData::write_extended_to_file(&original, &[long_trace()]).unwrap();
+        for extension in ["txt", "toml", "yaml"] {
+            let converted = temp_file(&format!("long-trace.{}", extension));
+            let copy = temp_file(&format!("long-trace.{}.xdat", extension));
+            write_processes(&converted, &read_processes(&original).unwrap()).unwrap();
+            write_processes(&copy, &read_processes(&converted).unwrap()).unwrap();
+            let bytes = fs::read(&copy).unwrap();
+            fs::remove_file(&converted).unwrap();
+            fs::remove_file(&copy).unwrap();
+            assert!(bytes == fs::read(&original).unwrap(), "{}", extension);
+        }
+        fs::remove_file(&original).unwrap();
+    }
+
+    /** Checks text fails to parse with an error for the given line */
+    fn assert_text_error<T: std::fmt::Debug>(result: Result<T, RecordError>, expected: &str) {
+        match result {
+            Err(RecordError::Text(message)) => assert_eq!(message, expected),
+            other => panic!("expected '{}', got {:?}", expected, other),
+        }
+    }
+
+    #[test]
+    fn malformed_params_rows_give_their_line_number() {
+        let heading = "Operating System Parameters:\n   Mem   Blk\n\n";
+        for (row, expected) in [
+            (
+                " 1) F 5000 8 5 FIFO 0 3 2 1",
+                "line 4: expected 10 parameter columns, got 9",
+            ),
+            (
+                " 1) F 5000 8 5 FIFO x 3 2 1 25",
+                "line 4: Init Qtm. must be a number, got 'x'",
+            ),
+            (
+                " 1) Q 5000 8 5 FIFO 0 3 2 1 25",
+                "line 4: unknown memory model 'Q'",
+            ),
+            (
+                " 1) F 5000 8 5 SJF 0 3 2 1 25",
+                "line 4: unknown algorithm 'SJF'",
+            ),
+        ] {
+            let text = format!("{}{}\n", heading, row);
+            assert_text_error(parse_params_table(&text), expected);
+        }
+    }
+
+    #[test]
+    fn malformed_input_rows_give_their_line_number() {
+        let heading = "Input Data for Problem:\n\n 1) GOODPGM 40 3 10, 20-1| 8\n";
+        for (row, expected) in [
+            (
+                " 2) SHORT 40",
+                "line 4: expected a name, memory needs and priority",
+            ),
+            (
+                " 2) BADMEM x 3 10",
+                "line 4: Memory Needs must be a number, got 'x'",
+            ),
+            (
+                " 2) NODEV 40 3 10, 20| 8",
+                "line 4: expected IO-DEVICE, got '20'",
+            ),
+            (" 2) BADDEV 40 3 10, 20-7", "line 4: unknown IO device 7"),
+            (
+                " 2) BADCPU 40 3 x, 20-1",
+                "line 4: CPU units must be a number, got 'x'",
+            ),
+        ] {
+            let text = format!("{}{}\n", heading, row);
+            assert_text_error(parse_input_table(&text), expected);
+        }
+    }
+
+    #[test]
+    fn malformed_toml_and_yaml_say_where() {
+        let toml = "[[process]]\nname = \"A\"\npriority = 1\nmemsize = \nrun_info = []\n";
+        match from_toml::<InputFile>(toml) {
+            Err(RecordError::Text(message)) => assert!(message.contains("line 4"), "{}", message),
+            _ => panic!("expected a TOML error"),
+        }
+        let yaml = "process:\n  - name: A\n    priority: one\n    memsize: 40\n    run_info: []\n";
+        match from_yaml::<InputFile>(yaml) {
+            Err(RecordError::Text(message)) => assert!(message.contains("line 3"), "{}", message),
+            _ => panic!("expected a YAML error"),
+        }
+        // unknown device codes are caught when the entries become records
+        let yaml =
+            "process:\n  - name: A\n    priority: 1\n    memsize: 40\n    run_info: [[5, 10, 9]]\n";
+        let result = from_yaml::<InputFile>(yaml).unwrap().into_records();
+        assert!(matches!(
+            result,
+            Err(RecordError::InvalidField { record: 0, ref field, ref value })
+                if field == "run_info[0][2]" && value == "9"
+        ));
+    }
+
+    #[test]
+    fn more_than_max_size_processes_is_an_error_in_every_format() {
+        let records = vec![ProcessData::with_memsize(10); MAX_SIZE + 1];
+        for name in [
+            "many.DAT",
+            "many.xdat",
+            "many.txt",
+            "many.toml",
+            "many.yaml",
+        ] {
+            let filename = temp_file(name);
+            write_processes(&filename, &records).unwrap();
+            let result = read_processes(&filename);
+            fs::remove_file(&filename).unwrap();
+            match result {
+                Err(RecordError::TooManyRecords { count, max }) => {
+                    assert_eq!((count, max), (MAX_SIZE + 1, MAX_SIZE), "{}", name)
+                }
+                other => panic!("{}: expected too many records, got {:?}", name, other),
+            }
+        }
+    }
+}